@@ -1,15 +1,25 @@
 use crate::{
+    auth::SshConfig,
+    credentials::CredentialSource,
+    deleted_refs::DeletedRefPolicy,
     filter::{Chain, FilterRepository},
-    matching_rules::Ruleset,
+    matching_rules::{Ruleset, presets},
+    ref_rewrite::RefRewrites,
+    remote_address::RemoteAddress,
+    retry::{RetryPolicy, Timeouts, with_async_timeout},
+    snapshot::SnapshotPolicy,
     source::{Provider, SourceRepositoryMappingProducer},
-    util::safe_display_url,
 };
 use anyhow::{Result, anyhow};
 use futures::stream::{self, StreamExt};
 use log::{error, warn};
 use serde::Deserialize;
-use std::{collections::HashSet, fmt, fs, path::PathBuf};
-use url::Url;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs,
+    path::PathBuf,
+    time::Duration,
+};
 
 fn get_config_paths(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
     let mut config_files = Vec::new();
@@ -78,7 +88,16 @@ pub(crate) trait RepositoryMappingProducer {
 pub(crate) struct RepositoryMapping {
     pub path: PathBuf,
     pub ref_match: Ruleset,
-    pub git_url: Url,
+    pub ref_rewrites: RefRewrites,
+    pub follow_tags: bool,
+    pub deleted_refs: DeletedRefPolicy,
+    pub snapshots: SnapshotPolicy,
+    pub refuse_non_fast_forward: bool,
+    pub retry: RetryPolicy,
+    pub timeout: Option<Duration>,
+    pub ssh: SshConfig,
+    pub credentials: Option<CredentialSource>,
+    pub git_url: RemoteAddress,
 }
 
 impl fmt::Display for RepositoryMapping {
@@ -86,7 +105,7 @@ impl fmt::Display for RepositoryMapping {
         write!(
             f,
             "{} => {}",
-            safe_display_url(self.git_url.clone()),
+            self.git_url.safe_display(),
             self.path.display(),
         )
     }
@@ -105,21 +124,11 @@ impl Config {
             return Err(anyhow!("No configuration files found"));
         }
 
-        let mut providers = Vec::new();
+        let mut files = Vec::new();
 
         for file_path in config_files {
             match fs::read_to_string(&file_path) {
-                Ok(s) => match toml::from_str::<ProviderConfig>(&s) {
-                    Ok(provider) => providers.push(provider),
-                    Err(e) => {
-                        error!("Failed to parse config file {}: {}", file_path.display(), e);
-                        return Err(anyhow!(
-                            "Failed to parse config file {}: {}",
-                            file_path.display(),
-                            e
-                        ));
-                    }
-                },
+                Ok(s) => files.push((file_path, s)),
                 Err(e) => {
                     error!("Failed to read file {}: {}", file_path.display(), e);
                     return Err(anyhow!(
@@ -131,6 +140,62 @@ impl Config {
             }
         }
 
+        // Presets may be used by any file, so collect them from all files first
+        let mut presets = HashMap::new();
+        let mut provider_files = Vec::new();
+
+        for (file_path, s) in &files {
+            let parse_error = |e: toml::de::Error| {
+                error!("Failed to parse config file {}: {}", file_path.display(), e);
+                anyhow!("Failed to parse config file {}: {}", file_path.display(), e)
+            };
+            let file = toml::from_str::<PresetsConfig>(s).map_err(parse_error)?;
+            let keys = toml::from_str::<toml::Table>(s).map_err(parse_error)?;
+
+            for (name, ruleset) in file.ref_matcher_presets {
+                if presets::builtin_names().any(|n| n == name) || presets.contains_key(&name) {
+                    return Err(anyhow!(
+                        "Duplicate ref matcher preset '{}' in config file {}",
+                        name,
+                        file_path.display()
+                    ));
+                }
+                presets.insert(name, ruleset);
+            }
+
+            // Anything other than presets makes this a provider file, which must then parse as one
+            if keys.keys().any(|k| k != PRESETS_KEY) {
+                provider_files.push((file_path, s));
+            } else if keys.is_empty() {
+                return Err(anyhow!(
+                    "Config file {} defines neither a provider nor {}",
+                    file_path.display(),
+                    PRESETS_KEY
+                ));
+            }
+        }
+
+        let mut providers = Vec::new();
+
+        for (file_path, s) in provider_files {
+            match presets::with_presets(&presets, || toml::from_str::<ProviderConfig>(s)) {
+                Ok(mut provider) => {
+                    provider
+                        .repo_filters
+                        .set_remote_auth(&provider.ssh, provider.credentials.as_ref());
+                    providers.push(provider);
+                }
+                Err(e) => {
+                    error!("Failed to parse config file {}: {}", file_path.display(), e);
+                    return Err(anyhow!(
+                        "Failed to parse config file {}: {}",
+                        file_path.display(),
+                        e
+                    ));
+                }
+            }
+        }
+
         Ok(Self { providers })
     }
 }
@@ -145,10 +210,45 @@ impl RepositoryMappingProducer for Config {
     }
 }
 
+const PRESETS_KEY: &str = "ref_matcher_presets";
+
+/// The parts of a config file that are read before any provider is parsed. A file containing
+/// nothing but these only defines presets, any other file must be a provider.
+#[derive(Deserialize)]
+struct PresetsConfig {
+    #[serde(default)]
+    ref_matcher_presets: HashMap<String, Ruleset>,
+}
+
 #[derive(Debug, Deserialize)]
 struct ProviderConfig {
     path: PathBuf,
     ref_matchers: Ruleset,
+    #[serde(default)]
+    ref_rewrites: RefRewrites,
+    /// Also mirror tags pointing at objects in the mirrored refs, even if not matched
+    #[serde(default)]
+    follow_tags: bool,
+    /// What to do with mirrored refs that were deleted from the remote
+    #[serde(default)]
+    deleted_refs: DeletedRefPolicy,
+    /// How long snapshots of the refs of each mirror are kept
+    #[serde(default)]
+    snapshots: SnapshotPolicy,
+    /// Fail instead of moving tags, or updating other refs to a target that does not descend from
+    /// their previous one
+    #[serde(default)]
+    refuse_non_fast_forward: bool,
+    /// How failed discovery and mirroring is retried
+    #[serde(default)]
+    retry: RetryPolicy,
+    #[serde(default)]
+    timeouts: Timeouts,
+    /// Authentication and host key checking for SSH remotes
+    #[serde(default)]
+    ssh: SshConfig,
+    /// Credentials for fetching over HTTP(S), where the provider does not supply its own
+    credentials: Option<CredentialSource>,
     source: Provider,
     #[serde(default)]
     repo_filters: Chain,
@@ -156,10 +256,31 @@ struct ProviderConfig {
 
 impl RepositoryMappingProducer for ProviderConfig {
     async fn repository_mappings(&self) -> Vec<Result<RepositoryMapping>> {
-        match self.source.repository_mappings().await {
+        let discovered = self
+            .retry
+            .run("Repository discovery", || {
+                with_async_timeout(self.timeouts.discovery(), self.source.repository_mappings())
+            })
+            .await;
+
+        // Filters may connect to remotes, so are run where they can block
+        let filtered = match discovered {
+            Ok(m) => {
+                let filters = self.repo_filters.clone();
+                tokio::task::spawn_blocking(move || {
+                    m.into_iter()
+                        .filter(|r| filters.filter(r))
+                        .collect::<Vec<_>>()
+                })
+                .await
+                .map_err(anyhow::Error::from)
+            }
+            Err(e) => Err(e),
+        };
+
+        match filtered {
             Ok(m) => m
                 .into_iter()
-                .filter(|r| self.repo_filters.filter(r))
                 .map(|r| {
                     Ok(RepositoryMapping {
                         path: self.path.join(if r.path.has_root() {
@@ -171,11 +292,69 @@ impl RepositoryMappingProducer for ProviderConfig {
                             Some(m) => m,
                             None => self.ref_matchers.clone(),
                         },
+                        ref_rewrites: match r.ref_rewrites {
+                            Some(rw) => rw,
+                            None => self.ref_rewrites.clone(),
+                        },
+                        follow_tags: self.follow_tags,
+                        deleted_refs: self.deleted_refs.clone(),
+                        snapshots: self.snapshots.clone(),
+                        refuse_non_fast_forward: self.refuse_non_fast_forward,
+                        retry: self.retry.clone(),
+                        timeout: self.timeouts.mirror(),
+                        ssh: self.ssh.clone(),
+                        credentials: self.credentials.clone(),
                         git_url: r.git_url,
                     })
                 })
                 .collect(),
-            Err(e) => vec![Err(e)],
+            Err(e) => vec![Err(anyhow!(
+                "Failed to discover repositories for {}: {}",
+                self.path.display(),
+                e
+            ))],
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(files: &[&str]) -> Result<Config> {
+        let dir = tempfile::tempdir().unwrap();
+        for (i, contents) in files.iter().enumerate() {
+            fs::write(dir.path().join(format!("{}.toml", i)), contents).unwrap();
+        }
+        Config::load(&[dir.path().to_path_buf()])
+    }
+
+    const PROVIDER: &str = "
+        path = 'mirrors'
+        ref_matchers = 'mine'
+        [source]
+        type = 'static_list'
+        repos = []
+    ";
+
+    #[test]
+    fn presets_only_file() {
+        let config = load(&[
+            "[ref_matcher_presets.mine]\nrules = [{ type = 'exact', expr = 'refs/heads/main' }]",
+            PROVIDER,
+        ])
+        .unwrap();
+        assert_eq!(config.providers.len(), 1);
+    }
+
+    #[test]
+    fn provider_without_source() {
+        assert!(
+            load(&[
+                "path = 'mirrors'\nref_matchers = 'releases-only'\n[sorce]\ntype = 'static_list'"
+            ])
+            .is_err()
+        );
+        assert!(load(&[""]).is_err());
+    }
+}
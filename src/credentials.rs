@@ -0,0 +1,284 @@
+use anyhow::{Result, anyhow};
+use serde::{
+    Deserialize, Deserializer,
+    de::{MapAccess, Visitor, value::MapAccessDeserializer},
+};
+use std::{env, fmt, fs, path::PathBuf, process::Command};
+use url::Url;
+
+/// A secret, and the user it belongs to if the source knows it.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Credential {
+    pub username: Option<String>,
+    pub secret: String,
+}
+
+impl Credential {
+    fn secret(secret: String) -> Self {
+        Self {
+            username: None,
+            secret,
+        }
+    }
+}
+
+/// Where a secret, such as an API token or HTTP password, is read from.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", remote = "Self")]
+pub(crate) enum CredentialSource {
+    /// Environment variable
+    Env { name: String },
+    /// File containing the secret, relative paths are relative to systemd's
+    /// `$CREDENTIALS_DIRECTORY` if set
+    File { path: PathBuf },
+    /// Standard output of a command (program and arguments, not run by a shell)
+    Command { command: Vec<String> },
+    /// The credential helpers configured in git, queried for the given URL or else the URL the
+    /// credential is needed for
+    GitCredential { url: Option<Url> },
+    /// A `.netrc` file (defaults to `$NETRC` or `~/.netrc`), looked up by the given machine or
+    /// else the host the credential is needed for
+    Netrc {
+        path: Option<PathBuf>,
+        machine: Option<String>,
+    },
+}
+
+impl CredentialSource {
+    /// Reads the credential needed to access `url`.
+    pub(crate) fn get(&self, url: &Url) -> Result<Credential> {
+        match self {
+            Self::Env { name } => env::var(name)
+                .map(Credential::secret)
+                .map_err(|_| anyhow!("Credential environment variable '{}' not set", name)),
+            Self::File { path } => {
+                let path = match env::var_os("CREDENTIALS_DIRECTORY") {
+                    Some(dir) if path.is_relative() => PathBuf::from(dir).join(path),
+                    _ => path.clone(),
+                };
+                let secret = fs::read_to_string(&path).map_err(|e| {
+                    anyhow!("Failed to read credential file {}: {}", path.display(), e)
+                })?;
+                Ok(Credential::secret(trim_newline(secret)))
+            }
+            Self::Command { command } => {
+                let (program, args) = command
+                    .split_first()
+                    .ok_or_else(|| anyhow!("Credential command is empty"))?;
+                let output = Command::new(program).args(args).output()?;
+                if !output.status.success() {
+                    return Err(anyhow!(
+                        "Credential command '{}' failed ({}): {}",
+                        program,
+                        output.status,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    ));
+                }
+                Ok(Credential::secret(trim_newline(String::from_utf8(
+                    output.stdout,
+                )?)))
+            }
+            Self::GitCredential { url: helper_url } => {
+                let mut url = helper_url.as_ref().unwrap_or(url).clone();
+                // Helpers store credentials for HTTP(S), which is also how forge APIs are accessed
+                if !url.scheme().starts_with("http") {
+                    url = Url::parse(&format!(
+                        "https://{}{}",
+                        url.host_str().unwrap_or_default(),
+                        url.path()
+                    ))?;
+                }
+                let (username, secret) = git2::CredentialHelper::new(url.as_str())
+                    .config(&git2::Config::open_default()?)
+                    .execute()
+                    .ok_or_else(|| anyhow!("No git credential found for {}", url))?;
+                Ok(Credential {
+                    username: Some(username),
+                    secret,
+                })
+            }
+            Self::Netrc { path, machine } => {
+                let path = match path {
+                    Some(p) => p.clone(),
+                    None => default_netrc_path()?,
+                };
+                let machine = match machine {
+                    Some(m) => m.as_str(),
+                    None => url
+                        .host_str()
+                        .ok_or_else(|| anyhow!("No host to look up in netrc for {}", url))?,
+                };
+                let contents = fs::read_to_string(&path)
+                    .map_err(|e| anyhow!("Failed to read netrc file {}: {}", path.display(), e))?;
+                netrc_lookup(&contents, machine)
+                    .ok_or_else(|| anyhow!("No password for {} in {}", machine, path.display()))
+            }
+        }
+    }
+}
+
+/// A credential source is either given as a table, or as the name of an environment variable
+/// (as the `token_env_var` options it replaces were).
+impl<'de> Deserialize<'de> for CredentialSource {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(CredentialSourceVisitor)
+    }
+}
+
+struct CredentialSourceVisitor;
+
+impl<'de> Visitor<'de> for CredentialSourceVisitor {
+    type Value = CredentialSource;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "a table containing `type`, or the name of an environment variable"
+        )
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(CredentialSource::Env {
+            name: v.to_string(),
+        })
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        // The derived (remote = "Self") implementation
+        CredentialSource::deserialize(MapAccessDeserializer::new(map))
+    }
+}
+
+fn trim_newline(s: String) -> String {
+    s.trim_end_matches(['\r', '\n']).to_string()
+}
+
+fn default_netrc_path() -> Result<PathBuf> {
+    if let Some(p) = env::var_os("NETRC") {
+        return Ok(p.into());
+    }
+    env::home_dir()
+        .map(|h| h.join(".netrc"))
+        .ok_or_else(|| anyhow!("Cannot determine home directory to find .netrc"))
+}
+
+/// Finds the login and password for `machine` in the contents of a netrc file, falling back to
+/// the `default` entry.
+fn netrc_lookup(contents: &str, machine: &str) -> Option<Credential> {
+    struct Entry<'a> {
+        /// `None` for the `default` entry
+        machine: Option<&'a str>,
+        login: Option<&'a str>,
+        password: Option<&'a str>,
+    }
+
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut tokens = contents.split_whitespace();
+    while let Some(token) = tokens.next() {
+        match token {
+            "machine" | "default" => entries.push(Entry {
+                machine: match token {
+                    "machine" => Some(tokens.next()?),
+                    _ => None,
+                },
+                login: None,
+                password: None,
+            }),
+            "login" | "password" | "account" => {
+                let value = tokens.next()?;
+                if let Some(e) = entries.last_mut() {
+                    match token {
+                        "login" => e.login = Some(value),
+                        "password" => e.password = Some(value),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let entry = entries
+        .iter()
+        .find(|e| e.machine == Some(machine))
+        .or_else(|| entries.iter().find(|e| e.machine.is_none()))?;
+    Some(Credential {
+        username: entry.login.map(String::from),
+        secret: entry.password?.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url() -> Url {
+        Url::parse("https://github.com/dannixon/repo").unwrap()
+    }
+
+    #[test]
+    fn deserialize() {
+        #[derive(Deserialize)]
+        struct T {
+            token: CredentialSource,
+        }
+
+        let t: T = toml::from_str("token = 'GITHUB_TOKEN'").unwrap();
+        assert!(matches!(t.token, CredentialSource::Env { name } if name == "GITHUB_TOKEN"));
+
+        let t: T = toml::from_str("token = { type = 'file', path = 'github' }").unwrap();
+        assert!(matches!(t.token, CredentialSource::File { .. }));
+
+        assert!(toml::from_str::<T>("token = { type = 'nope' }").is_err());
+    }
+
+    #[test]
+    fn file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("token");
+        fs::write(&path, "secret\n").unwrap();
+
+        let c = CredentialSource::File { path }.get(&url()).unwrap();
+        assert_eq!(c, Credential::secret("secret".into()));
+    }
+
+    #[test]
+    fn command() {
+        let c = CredentialSource::Command {
+            command: vec!["echo".into(), "secret".into()],
+        };
+        assert_eq!(c.get(&url()).unwrap(), Credential::secret("secret".into()));
+
+        let c = CredentialSource::Command {
+            command: vec!["false".into()],
+        };
+        assert!(c.get(&url()).is_err());
+    }
+
+    #[test]
+    fn netrc() {
+        let contents = "
+            machine gitlab.com login someone password one
+            machine github.com
+              login other
+              password two
+            default login anon password three
+        ";
+        let get = |m: &str| netrc_lookup(contents, m).unwrap();
+
+        assert_eq!(get("github.com").username.as_deref(), Some("other"));
+        assert_eq!(get("github.com").secret, "two");
+        assert_eq!(get("gitlab.com").secret, "one");
+        assert_eq!(get("example.com").secret, "three");
+        assert!(netrc_lookup("machine github.com login x", "github.com").is_none());
+    }
+}
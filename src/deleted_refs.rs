@@ -0,0 +1,393 @@
+use crate::{matching_rules::Ruleset, ref_rewrite::RefRewrites};
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use git2::{Oid, Repository};
+use serde::Deserialize;
+use std::{collections::HashSet, fmt};
+
+/// Namespace for refs that git-collage creates itself, these are never pruned.
+pub(crate) const COLLAGE_REF_PREFIX: &str = "refs/collage/";
+
+const ARCHIVE_REF_PREFIX: &str = "refs/collage/deleted/";
+
+/// What to do with mirrored local refs that no longer exist on the remote.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(tag = "policy", rename_all = "snake_case")]
+pub(crate) enum DeletedRefPolicy {
+    /// Leave the local ref in place
+    #[default]
+    Keep,
+    /// Delete the local ref
+    Delete,
+    /// Move the local ref to `refs/collage/deleted/<date>/<name>/<oid>`, deleting it from there
+    /// once it is older than `expire_after_days` (if given)
+    Archive { expire_after_days: Option<u32> },
+}
+
+pub(crate) struct DeletedRef {
+    name: String,
+    oid: Oid,
+    action: DeletedRefAction,
+}
+
+enum DeletedRefAction {
+    Kept,
+    Deleted,
+    Archived(String),
+    Expired,
+}
+
+impl fmt::Display for DeletedRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[del] {} : {}", self.oid, self.name)?;
+        match &self.action {
+            DeletedRefAction::Kept => write!(f, " (kept)"),
+            DeletedRefAction::Deleted => Ok(()),
+            DeletedRefAction::Archived(to) => write!(f, " => {}", to),
+            DeletedRefAction::Expired => write!(f, " (expired)"),
+        }
+    }
+}
+
+impl DeletedRefPolicy {
+    /// Applies the policy to the local refs that are mirrored from the remote, i.e. whose remote
+    /// names (see `ref_rewrites`) could be selected by `ref_match`, but are not in `upstream`, the
+    /// local names of all refs the remote currently has.
+    /// Any other local refs, such as notes added to the mirror, are left alone.
+    pub(crate) fn apply(
+        &self,
+        repo: &Repository,
+        upstream: &HashSet<String>,
+        ref_match: &Ruleset,
+        ref_rewrites: &RefRewrites,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<DeletedRef>> {
+        let mut gone = Vec::new();
+        let mut archived = Vec::new();
+
+        for r in repo.references()? {
+            let r = r?;
+            let (Some(name), Some(oid)) = (r.name(), r.target()) else {
+                continue;
+            };
+
+            if let Some(rest) = name.strip_prefix(ARCHIVE_REF_PREFIX) {
+                archived.push((
+                    name.to_string(),
+                    oid,
+                    rest.split('/').next().map(String::from),
+                ));
+            } else if !name.starts_with(COLLAGE_REF_PREFIX) && !upstream.contains(name) {
+                gone.push((name.to_string(), oid));
+            }
+        }
+
+        let remote_names: Vec<(String, Oid)> = gone
+            .iter()
+            .flat_map(|(name, oid)| {
+                ref_rewrites
+                    .remote_names(name)
+                    .into_iter()
+                    .map(|n| (n, *oid))
+            })
+            .collect();
+        let mirrored: HashSet<String> = ref_match
+            .candidates(remote_names.iter().map(|(n, oid)| (n.as_str(), *oid)))
+            .into_iter()
+            .map(|(n, _)| ref_rewrites.local_name(n))
+            .collect();
+        let deleted = gone.into_iter().filter(|(name, _)| mirrored.contains(name));
+
+        let mut reports = Vec::new();
+
+        for (name, oid) in deleted {
+            let action = match self {
+                Self::Keep => DeletedRefAction::Kept,
+                Self::Delete => {
+                    repo.find_reference(&name)?.delete()?;
+                    DeletedRefAction::Deleted
+                }
+                Self::Archive { .. } => {
+                    // The target is part of the name so that a ref deleted again on the same day
+                    // does not replace the earlier archive
+                    let to = format!(
+                        "{}{}/{}/{}",
+                        ARCHIVE_REF_PREFIX,
+                        now.format("%Y-%m-%d"),
+                        name.strip_prefix("refs/").unwrap_or(&name),
+                        oid
+                    );
+                    if repo.refname_to_id(&to).is_ok() {
+                        // Already archived with the same target
+                        repo.find_reference(&name)?.delete()?;
+                    } else {
+                        repo.find_reference(&name)?.rename(
+                            &to,
+                            false,
+                            "git-collage archive deleted ref",
+                        )?;
+                    }
+                    DeletedRefAction::Archived(to)
+                }
+            };
+            reports.push(DeletedRef { name, oid, action });
+        }
+
+        if let Self::Archive {
+            expire_after_days: Some(days),
+        } = self
+        {
+            for (name, oid, date) in archived {
+                let date = date.and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok());
+                if date.is_some_and(|d| (now.date_naive() - d).num_days() > i64::from(*days)) {
+                    repo.find_reference(&name)?.delete()?;
+                    reports.push(DeletedRef {
+                        name,
+                        oid,
+                        action: DeletedRefAction::Expired,
+                    });
+                }
+            }
+        }
+
+        Ok(reports)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::testing::{commit, repo_with_commit};
+    use chrono::TimeZone;
+    use std::collections::HashMap;
+
+    fn upstream(names: &[&str]) -> HashSet<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    fn branches_and_tags() -> Ruleset {
+        toml::from_str::<HashMap<String, Ruleset>>("rules = 'all-branches-and-tags'").unwrap()
+            ["rules"]
+            .clone()
+    }
+
+    fn ref_names(repo: &Repository) -> Vec<String> {
+        let mut names: Vec<String> = repo
+            .references()
+            .unwrap()
+            .map(|r| r.unwrap().name().unwrap().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn setup(dir: &std::path::Path) -> Repository {
+        let (repo, oid) = repo_with_commit(dir, 0);
+        repo.reference("refs/heads/gone", oid, false, "").unwrap();
+        repo.reference("refs/collage/other", oid, false, "")
+            .unwrap();
+        repo
+    }
+
+    #[test]
+    fn keep() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = setup(dir.path());
+
+        let reports = DeletedRefPolicy::Keep
+            .apply(
+                &repo,
+                &upstream(&["refs/heads/main"]),
+                &branches_and_tags(),
+                &RefRewrites::default(),
+                Utc::now(),
+            )
+            .unwrap();
+        assert_eq!(reports.len(), 1);
+        assert!(
+            reports[0]
+                .to_string()
+                .ends_with(" : refs/heads/gone (kept)")
+        );
+        assert_eq!(
+            ref_names(&repo),
+            vec!["refs/collage/other", "refs/heads/gone", "refs/heads/main"]
+        );
+    }
+
+    #[test]
+    fn delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = setup(dir.path());
+
+        let reports = DeletedRefPolicy::Delete
+            .apply(
+                &repo,
+                &upstream(&["refs/heads/main"]),
+                &branches_and_tags(),
+                &RefRewrites::default(),
+                Utc::now(),
+            )
+            .unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(
+            ref_names(&repo),
+            vec!["refs/collage/other", "refs/heads/main"]
+        );
+    }
+
+    #[test]
+    fn delete_only_mirrored() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = setup(dir.path());
+        let oid = repo.refname_to_id("refs/heads/gone").unwrap();
+        repo.reference("refs/notes/mine", oid, false, "").unwrap();
+
+        let reports = DeletedRefPolicy::Delete
+            .apply(
+                &repo,
+                &upstream(&["refs/heads/main"]),
+                &branches_and_tags(),
+                &RefRewrites::default(),
+                Utc::now(),
+            )
+            .unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(
+            ref_names(&repo),
+            vec!["refs/collage/other", "refs/heads/main", "refs/notes/mine"]
+        );
+    }
+
+    #[test]
+    fn archive_and_expire() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = setup(dir.path());
+        let policy = DeletedRefPolicy::Archive {
+            expire_after_days: Some(7),
+        };
+        let day = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+
+        let oid = repo.refname_to_id("refs/heads/gone").unwrap();
+        let archived = format!("refs/collage/deleted/2024-03-01/heads/gone/{}", oid);
+
+        let reports = policy
+            .apply(
+                &repo,
+                &upstream(&["refs/heads/main"]),
+                &branches_and_tags(),
+                &RefRewrites::default(),
+                day,
+            )
+            .unwrap();
+        assert_eq!(reports.len(), 1);
+        assert!(
+            reports[0]
+                .to_string()
+                .ends_with(&format!(" : refs/heads/gone => {}", archived))
+        );
+        assert_eq!(
+            ref_names(&repo),
+            vec![archived.as_str(), "refs/collage/other", "refs/heads/main"]
+        );
+
+        // Not yet expired
+        let reports = policy
+            .apply(
+                &repo,
+                &upstream(&["refs/heads/main"]),
+                &branches_and_tags(),
+                &RefRewrites::default(),
+                day + chrono::TimeDelta::days(7),
+            )
+            .unwrap();
+        assert!(reports.is_empty());
+
+        let reports = policy
+            .apply(
+                &repo,
+                &upstream(&["refs/heads/main"]),
+                &branches_and_tags(),
+                &RefRewrites::default(),
+                day + chrono::TimeDelta::days(8),
+            )
+            .unwrap();
+        assert_eq!(reports.len(), 1);
+        assert!(reports[0].to_string().ends_with(" (expired)"));
+        assert_eq!(
+            ref_names(&repo),
+            vec!["refs/collage/other", "refs/heads/main"]
+        );
+    }
+
+    #[test]
+    fn archive_same_day() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = setup(dir.path());
+        let policy = DeletedRefPolicy::Archive {
+            expire_after_days: None,
+        };
+        let first = repo.refname_to_id("refs/heads/gone").unwrap();
+
+        policy
+            .apply(
+                &repo,
+                &upstream(&["refs/heads/main"]),
+                &branches_and_tags(),
+                &RefRewrites::default(),
+                Utc::now(),
+            )
+            .unwrap();
+
+        // Recreated and deleted again, once with a different target and once with the same
+        let second = commit(&repo, "refs/heads/gone", 1, &[first]);
+        policy
+            .apply(
+                &repo,
+                &upstream(&["refs/heads/main"]),
+                &branches_and_tags(),
+                &RefRewrites::default(),
+                Utc::now(),
+            )
+            .unwrap();
+        repo.reference("refs/heads/gone", first, false, "").unwrap();
+        policy
+            .apply(
+                &repo,
+                &upstream(&["refs/heads/main"]),
+                &branches_and_tags(),
+                &RefRewrites::default(),
+                Utc::now(),
+            )
+            .unwrap();
+
+        let archived: HashSet<Oid> = repo
+            .references_glob(&format!("{}*", ARCHIVE_REF_PREFIX))
+            .unwrap()
+            .map(|r| r.unwrap().target().unwrap())
+            .collect();
+        assert_eq!(archived, HashSet::from([first, second]));
+        assert!(repo.refname_to_id("refs/heads/gone").is_err());
+    }
+
+    #[test]
+    fn deserialize() {
+        #[derive(Deserialize)]
+        struct Config {
+            deleted_refs: DeletedRefPolicy,
+        }
+
+        let c: Config = toml::from_str("deleted_refs = { policy = 'delete' }").unwrap();
+        assert!(matches!(c.deleted_refs, DeletedRefPolicy::Delete));
+
+        let c: Config =
+            toml::from_str("deleted_refs = { policy = 'archive', expire_after_days = 30 }")
+                .unwrap();
+        assert!(matches!(
+            c.deleted_refs,
+            DeletedRefPolicy::Archive {
+                expire_after_days: Some(30)
+            }
+        ));
+    }
+}
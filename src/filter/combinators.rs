@@ -0,0 +1,170 @@
+use super::{Filter, FilterRepository};
+use crate::{auth::SshConfig, credentials::CredentialSource, source::SourceRepositoryMapping};
+use serde::Deserialize;
+
+/// Keeps repositories that pass every one of the nested filters.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct All {
+    filters: Vec<Filter>,
+}
+
+impl FilterRepository for All {
+    fn filter(&self, r: &SourceRepositoryMapping) -> bool {
+        self.filters.iter().all(|f| f.filter(r))
+    }
+
+    fn set_remote_auth(&mut self, ssh: &SshConfig, credentials: Option<&CredentialSource>) {
+        for f in &mut self.filters {
+            f.set_remote_auth(ssh, credentials);
+        }
+    }
+}
+
+/// Keeps repositories that pass at least one of the nested filters.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct Any {
+    filters: Vec<Filter>,
+}
+
+impl FilterRepository for Any {
+    fn filter(&self, r: &SourceRepositoryMapping) -> bool {
+        self.filters.iter().any(|f| f.filter(r))
+    }
+
+    fn set_remote_auth(&mut self, ssh: &SshConfig, credentials: Option<&CredentialSource>) {
+        for f in &mut self.filters {
+            f.set_remote_auth(ssh, credentials);
+        }
+    }
+}
+
+/// Keeps repositories that do not pass the nested filter.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct Not {
+    filter: Box<Filter>,
+}
+
+impl FilterRepository for Not {
+    fn filter(&self, r: &SourceRepositoryMapping) -> bool {
+        !self.filter.filter(r)
+    }
+
+    fn set_remote_auth(&mut self, ssh: &SshConfig, credentials: Option<&CredentialSource>) {
+        self.filter.set_remote_auth(ssh, credentials);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        filter::{blocklist::Blocklist, url_path_prefix::UrlPathPrefix},
+        util::testing::source_mapping,
+    };
+
+    fn mapping(url: &str) -> SourceRepositoryMapping {
+        source_mapping(url, Default::default())
+    }
+
+    fn prefix(p: &str) -> Filter {
+        Filter::UrlPathPrefix(UrlPathPrefix {
+            prefixes: vec![p.to_string()],
+        })
+    }
+
+    fn blocklist(url: &str) -> Filter {
+        Filter::Blocklist(Blocklist {
+            urls: vec![url::Url::parse(url).unwrap().into()],
+        })
+    }
+
+    #[test]
+    fn all() {
+        let filter = All {
+            filters: vec![prefix("/one"), blocklist("https://github.com/one/nope")],
+        };
+
+        assert!(filter.filter(&mapping("https://github.com/one/repo")));
+        assert!(!filter.filter(&mapping("https://github.com/one/nope")));
+        assert!(!filter.filter(&mapping("https://github.com/two/repo")));
+    }
+
+    #[test]
+    fn all_empty() {
+        let filter = All { filters: vec![] };
+        assert!(filter.filter(&mapping("https://github.com/one/repo")));
+    }
+
+    #[test]
+    fn any() {
+        let filter = Any {
+            filters: vec![prefix("/one"), prefix("/two")],
+        };
+
+        assert!(filter.filter(&mapping("https://github.com/one/repo")));
+        assert!(filter.filter(&mapping("https://github.com/two/repo")));
+        assert!(!filter.filter(&mapping("https://github.com/three/repo")));
+    }
+
+    #[test]
+    fn any_empty() {
+        let filter = Any { filters: vec![] };
+        assert!(!filter.filter(&mapping("https://github.com/one/repo")));
+    }
+
+    #[test]
+    fn not() {
+        let filter = Not {
+            filter: Box::new(prefix("/one")),
+        };
+
+        assert!(!filter.filter(&mapping("https://github.com/one/repo")));
+        assert!(filter.filter(&mapping("https://github.com/two/repo")));
+    }
+
+    #[test]
+    fn nested() {
+        // (prefix "/one" OR prefix "/two") AND NOT prefix "/two/secret"
+        let filter = All {
+            filters: vec![
+                Filter::Any(Any {
+                    filters: vec![prefix("/one"), prefix("/two")],
+                }),
+                Filter::Not(Not {
+                    filter: Box::new(prefix("/two/secret")),
+                }),
+            ],
+        };
+
+        assert!(filter.filter(&mapping("https://github.com/one/repo")));
+        assert!(filter.filter(&mapping("https://github.com/two/repo")));
+        assert!(!filter.filter(&mapping("https://github.com/two/secret")));
+        assert!(!filter.filter(&mapping("https://github.com/three/repo")));
+    }
+
+    #[test]
+    fn deserialize() {
+        let chain: crate::filter::Chain = toml::from_str(
+            r#"
+            [[filters]]
+            type = 'any'
+            [[filters.filters]]
+            type = 'url_path_prefix'
+            prefixes = ['/one']
+            [[filters.filters]]
+            type = 'url_path_prefix'
+            prefixes = ['/two']
+
+            [[filters]]
+            type = 'not'
+            filter = { type = 'url_path_prefix', prefixes = ['/two/secret'] }
+            "#,
+        )
+        .unwrap();
+
+        assert!(chain.filter(&mapping("https://github.com/one/repo")));
+        assert!(chain.filter(&mapping("https://github.com/two/repo")));
+        assert!(!chain.filter(&mapping("https://github.com/two/secret")));
+        assert!(!chain.filter(&mapping("https://github.com/three/repo")));
+    }
+}
@@ -0,0 +1,39 @@
+use super::FilterRepository;
+use crate::source::SourceRepositoryMapping;
+use serde::Deserialize;
+
+/// Removes repositories that the provider reports as being archived.
+/// Repositories with unknown archive status are kept.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct ExcludeArchived {}
+
+impl FilterRepository for ExcludeArchived {
+    fn filter(&self, r: &SourceRepositoryMapping) -> bool {
+        r.metadata.archived != Some(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{source::RepositoryMetadata, util::testing::source_mapping};
+
+    fn mapping(archived: Option<bool>) -> SourceRepositoryMapping {
+        source_mapping(
+            "https://github.com/dannixon/repo",
+            RepositoryMetadata {
+                archived,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn filter_basics() {
+        let filter = ExcludeArchived {};
+
+        assert!(filter.filter(&mapping(Some(false))));
+        assert!(!filter.filter(&mapping(Some(true))));
+        assert!(filter.filter(&mapping(None)));
+    }
+}
@@ -0,0 +1,39 @@
+use super::FilterRepository;
+use crate::source::SourceRepositoryMapping;
+use serde::Deserialize;
+
+/// Removes repositories that the provider reports as being forks.
+/// Repositories with unknown fork status are kept.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct ExcludeForks {}
+
+impl FilterRepository for ExcludeForks {
+    fn filter(&self, r: &SourceRepositoryMapping) -> bool {
+        r.metadata.fork != Some(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{source::RepositoryMetadata, util::testing::source_mapping};
+
+    fn mapping(fork: Option<bool>) -> SourceRepositoryMapping {
+        source_mapping(
+            "https://github.com/dannixon/repo",
+            RepositoryMetadata {
+                fork,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn filter_basics() {
+        let filter = ExcludeForks {};
+
+        assert!(filter.filter(&mapping(Some(false))));
+        assert!(!filter.filter(&mapping(Some(true))));
+        assert!(filter.filter(&mapping(None)));
+    }
+}
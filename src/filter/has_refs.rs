@@ -0,0 +1,161 @@
+use super::FilterRepository;
+use crate::{
+    auth::{RemoteAuth, SshConfig},
+    credentials::CredentialSource,
+    matching_rules::{Context, Ruleset},
+    source::SourceRepositoryMapping,
+};
+use anyhow::Result;
+use git2::{Direction, Remote};
+use serde::Deserialize;
+use tokio::runtime::Handle;
+
+/// Keeps only repositories whose remote advertises at least one ref matching the given rules.
+///
+/// This requires a connection to the remote for every repository it is evaluated against, so it
+/// is best placed after any cheaper filters. Remotes are authenticated as when mirroring.
+/// Rules that depend on commit times never match, as no objects are fetched. Rules that query the
+/// forge (i.e. `open_requests`) are resolved for each repository, so must run within the runtime.
+/// Repositories whose remote cannot be listed, or whose rules cannot be resolved, are kept, so
+/// that the failure is reported when mirroring.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct HasRefs {
+    ref_matchers: Ruleset,
+
+    #[serde(skip)]
+    ssh: Box<SshConfig>,
+    #[serde(skip)]
+    credentials: Option<CredentialSource>,
+}
+
+impl HasRefs {
+    fn any_remote_ref_matches(&self, r: &SourceRepositoryMapping) -> Result<bool> {
+        let mut ref_matchers = self.ref_matchers.clone();
+        if ref_matchers.needs_resolving() {
+            Handle::current().block_on(ref_matchers.resolve(r.git_url.url()))?;
+        }
+
+        let mut remote = Remote::create_detached(r.git_url.without_credentials().to_string())?;
+        let auth = RemoteAuth::new(&self.ssh, r.git_url.url(), self.credentials.as_ref());
+        let conn = remote.connect_auth(Direction::Fetch, Some(auth.callbacks()), None)?;
+
+        // See the note in `mirror::mirror` regarding peeled refs
+        let ctx = Context {
+            default_branch: conn
+                .default_branch()
+                .ok()
+                .and_then(|b| b.as_str().map(String::from)),
+            ..Default::default()
+        };
+
+        Ok(!ref_matchers
+            .select(
+                conn.list()?.iter().filter(|h| !h.name().ends_with("^{}")),
+                &ctx,
+            )
+            .is_empty())
+    }
+}
+
+impl FilterRepository for HasRefs {
+    fn filter(&self, r: &SourceRepositoryMapping) -> bool {
+        match self.any_remote_ref_matches(r) {
+            Ok(matched) => {
+                if !matched {
+                    log::debug!("No remote refs matched for {}", r.path.display());
+                }
+                matched
+            }
+            Err(e) => {
+                log::warn!(
+                    "Failed to match remote refs for {}: {}",
+                    r.path.display(),
+                    e
+                );
+                true
+            }
+        }
+    }
+
+    fn set_remote_auth(&mut self, ssh: &SshConfig, credentials: Option<&CredentialSource>) {
+        *self.ssh = ssh.clone();
+        self.credentials = credentials.cloned();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::testing::{repo_with_commit, source_mapping};
+
+    fn filter(rules: &str) -> HasRefs {
+        toml::from_str(rules).unwrap()
+    }
+
+    #[test]
+    fn filter_basics() {
+        let dir = tempfile::tempdir().unwrap();
+        let (repo, oid) = repo_with_commit(dir.path(), 0);
+        repo.reference("refs/tags/v1.0.0", oid, false, "").unwrap();
+
+        let m = source_mapping(dir.path().to_str().unwrap(), Default::default());
+
+        let has_tags = filter(
+            r#"
+            [[ref_matchers.rules]]
+            type = 'regex'
+            expr = '^refs/tags/v'
+            "#,
+        );
+        assert!(has_tags.filter(&m));
+
+        let has_develop = filter(
+            r#"
+            [[ref_matchers.rules]]
+            type = 'exact'
+            expr = 'refs/heads/develop'
+            "#,
+        );
+        assert!(!has_develop.filter(&m));
+    }
+
+    #[test]
+    fn filter_unreachable_remote_is_kept() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let m = source_mapping(
+            dir.path().join("missing").to_str().unwrap(),
+            Default::default(),
+        );
+
+        let has_anything = filter(
+            r#"
+            [[ref_matchers.rules]]
+            type = 'regex'
+            expr = '.*'
+            "#,
+        );
+        assert!(has_anything.filter(&m));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn filter_open_requests_are_resolved() {
+        let dir = tempfile::tempdir().unwrap();
+        repo_with_commit(dir.path(), 0);
+        let m = source_mapping(dir.path().to_str().unwrap(), Default::default());
+
+        // The forge cannot be determined from a path, so resolving fails rather than the rule
+        // silently matching nothing
+        let has_requests = filter(
+            r#"
+            [[ref_matchers.rules]]
+            type = 'open_requests'
+            expr = {}
+            "#,
+        );
+        let kept = tokio::task::spawn_blocking(move || has_requests.filter(&m))
+            .await
+            .unwrap();
+        assert!(kept);
+    }
+}
@@ -0,0 +1,47 @@
+use super::FilterRepository;
+use crate::source::SourceRepositoryMapping;
+use serde::Deserialize;
+
+/// Keeps only repositories whose primary language (as reported by the provider) is one of the
+/// given languages.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct Languages {
+    languages: Vec<String>,
+}
+
+impl FilterRepository for Languages {
+    fn filter(&self, r: &SourceRepositoryMapping) -> bool {
+        match &r.metadata.language {
+            Some(l) => self.languages.iter().any(|i| i.eq_ignore_ascii_case(l)),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{source::RepositoryMetadata, util::testing::source_mapping};
+
+    fn mapping(language: Option<&str>) -> SourceRepositoryMapping {
+        source_mapping(
+            "https://github.com/dannixon/repo",
+            RepositoryMetadata {
+                language: language.map(|l| l.to_string()),
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn filter_basics() {
+        let filter = Languages {
+            languages: vec!["rust".to_string(), "Python".to_string()],
+        };
+
+        assert!(filter.filter(&mapping(Some("Rust"))));
+        assert!(filter.filter(&mapping(Some("Python"))));
+        assert!(!filter.filter(&mapping(Some("C++"))));
+        assert!(!filter.filter(&mapping(None)));
+    }
+}
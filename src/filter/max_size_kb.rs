@@ -0,0 +1,42 @@
+use super::FilterRepository;
+use crate::source::SourceRepositoryMapping;
+use serde::Deserialize;
+
+/// Removes repositories larger than the given size (in KiB, as reported by the provider).
+/// Repositories of unknown size are kept.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct MaxSizeKb {
+    size: u64,
+}
+
+impl FilterRepository for MaxSizeKb {
+    fn filter(&self, r: &SourceRepositoryMapping) -> bool {
+        r.metadata.size_kb.is_none_or(|s| s <= self.size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{source::RepositoryMetadata, util::testing::source_mapping};
+
+    fn mapping(size_kb: Option<u64>) -> SourceRepositoryMapping {
+        source_mapping(
+            "https://github.com/dannixon/repo",
+            RepositoryMetadata {
+                size_kb,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn filter_basics() {
+        let filter = MaxSizeKb { size: 1024 };
+
+        assert!(filter.filter(&mapping(Some(512))));
+        assert!(filter.filter(&mapping(Some(1024))));
+        assert!(!filter.filter(&mapping(Some(1025))));
+        assert!(filter.filter(&mapping(None)));
+    }
+}
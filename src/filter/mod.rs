@@ -1,24 +1,47 @@
 mod blocklist;
+mod combinators;
+mod exclude_archived;
+mod exclude_forks;
+mod has_refs;
+mod languages;
+mod max_size_kb;
+mod pushed_within_days;
+mod script;
+mod topics_any;
 mod url_path_prefix;
 
-use crate::source::SourceRepositoryMapping;
+use crate::{auth::SshConfig, credentials::CredentialSource, source::SourceRepositoryMapping};
 use enum_dispatch::enum_dispatch;
 use serde::Deserialize;
 
 #[enum_dispatch(Filter)]
 pub(crate) trait FilterRepository {
     fn filter(&self, _: &SourceRepositoryMapping) -> bool;
+
+    /// Supplies the authentication of the provider, for filters that connect to remotes.
+    fn set_remote_auth(&mut self, _ssh: &SshConfig, _credentials: Option<&CredentialSource>) {}
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 #[enum_dispatch]
 enum Filter {
     Blocklist(blocklist::Blocklist),
     UrlPathPrefix(url_path_prefix::UrlPathPrefix),
+    ExcludeForks(exclude_forks::ExcludeForks),
+    ExcludeArchived(exclude_archived::ExcludeArchived),
+    MaxSizeKb(max_size_kb::MaxSizeKb),
+    PushedWithinDays(pushed_within_days::PushedWithinDays),
+    TopicsAny(topics_any::TopicsAny),
+    Languages(languages::Languages),
+    Script(script::Script),
+    HasRefs(has_refs::HasRefs),
+    All(combinators::All),
+    Any(combinators::Any),
+    Not(combinators::Not),
 }
 
-#[derive(Debug, Default, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize)]
 pub(crate) struct Chain {
     filters: Vec<Filter>,
 }
@@ -32,6 +55,12 @@ impl FilterRepository for Chain {
         }
         true
     }
+
+    fn set_remote_auth(&mut self, ssh: &SshConfig, credentials: Option<&CredentialSource>) {
+        for f in &mut self.filters {
+            f.set_remote_auth(ssh, credentials);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -44,30 +73,56 @@ mod tests {
         let chain = Chain {
             filters: vec![
                 Filter::Blocklist(blocklist::Blocklist {
-                    urls: vec![url::Url::parse("https://github.com/dannixon/nope").unwrap()],
+                    urls: vec![
+                        url::Url::parse("https://github.com/dannixon/nope")
+                            .unwrap()
+                            .into(),
+                    ],
                 }),
                 Filter::Blocklist(blocklist::Blocklist {
-                    urls: vec![url::Url::parse("https://github.com/dannixon/very_nope").unwrap()],
+                    urls: vec![
+                        url::Url::parse("https://github.com/dannixon/very_nope")
+                            .unwrap()
+                            .into(),
+                    ],
                 }),
             ],
         };
 
-        assert!(chain.filter(&SourceRepositoryMapping {
-            path: PathBuf::new(),
-            ref_match: None,
-            git_url: url::Url::parse("https://github.com/dannixon/yes").unwrap(),
-        }));
+        assert!(
+            chain.filter(&SourceRepositoryMapping {
+                path: PathBuf::new(),
+                ref_match: None,
+                ref_rewrites: None,
+                git_url: url::Url::parse("https://github.com/dannixon/yes")
+                    .unwrap()
+                    .into(),
+                metadata: Default::default(),
+            })
+        );
 
-        assert!(!chain.filter(&SourceRepositoryMapping {
-            path: PathBuf::new(),
-            ref_match: None,
-            git_url: url::Url::parse("https://github.com/dannixon/nope").unwrap(),
-        }));
+        assert!(
+            !chain.filter(&SourceRepositoryMapping {
+                path: PathBuf::new(),
+                ref_match: None,
+                ref_rewrites: None,
+                git_url: url::Url::parse("https://github.com/dannixon/nope")
+                    .unwrap()
+                    .into(),
+                metadata: Default::default(),
+            })
+        );
 
-        assert!(!chain.filter(&SourceRepositoryMapping {
-            path: PathBuf::new(),
-            ref_match: None,
-            git_url: url::Url::parse("https://github.com/dannixon/very_nope").unwrap(),
-        }));
+        assert!(
+            !chain.filter(&SourceRepositoryMapping {
+                path: PathBuf::new(),
+                ref_match: None,
+                ref_rewrites: None,
+                git_url: url::Url::parse("https://github.com/dannixon/very_nope")
+                    .unwrap()
+                    .into(),
+                metadata: Default::default(),
+            })
+        );
     }
 }
@@ -0,0 +1,44 @@
+use super::FilterRepository;
+use crate::source::SourceRepositoryMapping;
+use chrono::{TimeDelta, Utc};
+use serde::Deserialize;
+
+/// Keeps only repositories that have been pushed to within the given number of days.
+/// Repositories with an unknown last push time are kept.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct PushedWithinDays {
+    days: u32,
+}
+
+impl FilterRepository for PushedWithinDays {
+    fn filter(&self, r: &SourceRepositoryMapping) -> bool {
+        let cutoff = Utc::now() - TimeDelta::days(self.days.into());
+        r.metadata.pushed_at.is_none_or(|t| t >= cutoff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{source::RepositoryMetadata, util::testing::source_mapping};
+    use chrono::{DateTime, Utc};
+
+    fn mapping(pushed_at: Option<DateTime<Utc>>) -> SourceRepositoryMapping {
+        source_mapping(
+            "https://github.com/dannixon/repo",
+            RepositoryMetadata {
+                pushed_at,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn filter_basics() {
+        let filter = PushedWithinDays { days: 30 };
+
+        assert!(filter.filter(&mapping(Some(Utc::now() - TimeDelta::days(1)))));
+        assert!(!filter.filter(&mapping(Some(Utc::now() - TimeDelta::days(31)))));
+        assert!(filter.filter(&mapping(None)));
+    }
+}
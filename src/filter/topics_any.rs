@@ -0,0 +1,46 @@
+use super::FilterRepository;
+use crate::source::SourceRepositoryMapping;
+use serde::Deserialize;
+
+/// Keeps only repositories that are tagged with at least one of the given topics.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct TopicsAny {
+    topics: Vec<String>,
+}
+
+impl FilterRepository for TopicsAny {
+    fn filter(&self, r: &SourceRepositoryMapping) -> bool {
+        r.metadata
+            .topics
+            .iter()
+            .any(|t| self.topics.iter().any(|i| i.eq_ignore_ascii_case(t)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{source::RepositoryMetadata, util::testing::source_mapping};
+
+    fn mapping(topics: &[&str]) -> SourceRepositoryMapping {
+        source_mapping(
+            "https://github.com/dannixon/repo",
+            RepositoryMetadata {
+                topics: topics.iter().map(|t| t.to_string()).collect(),
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn filter_basics() {
+        let filter = TopicsAny {
+            topics: vec!["backup".to_string(), "rust".to_string()],
+        };
+
+        assert!(filter.filter(&mapping(&["backup"])));
+        assert!(filter.filter(&mapping(&["cli", "Rust"])));
+        assert!(!filter.filter(&mapping(&["cli"])));
+        assert!(!filter.filter(&mapping(&[])));
+    }
+}
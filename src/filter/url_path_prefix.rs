@@ -2,16 +2,16 @@ use super::FilterRepository;
 use crate::source::SourceRepositoryMapping;
 use serde::Deserialize;
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 pub(crate) struct UrlPathPrefix {
-    prefixes: Vec<String>,
+    pub(super) prefixes: Vec<String>,
 }
 
 impl FilterRepository for UrlPathPrefix {
     fn filter(&self, r: &SourceRepositoryMapping) -> bool {
         self.prefixes
             .iter()
-            .any(|p| r.git_url.path().starts_with(p))
+            .any(|p| r.git_url.url().path().starts_with(p))
     }
 }
 
@@ -26,22 +26,40 @@ mod tests {
             prefixes: vec!["/one".to_string(), "/three".to_string()],
         };
 
-        assert!(filter.filter(&SourceRepositoryMapping {
-            path: PathBuf::new(),
-            ref_match: None,
-            git_url: url::Url::parse("https://github.com/one/repo").unwrap(),
-        }));
+        assert!(
+            filter.filter(&SourceRepositoryMapping {
+                path: PathBuf::new(),
+                ref_match: None,
+                ref_rewrites: None,
+                git_url: url::Url::parse("https://github.com/one/repo")
+                    .unwrap()
+                    .into(),
+                metadata: Default::default(),
+            })
+        );
 
-        assert!(!filter.filter(&SourceRepositoryMapping {
-            path: PathBuf::new(),
-            ref_match: None,
-            git_url: url::Url::parse("https://github.com/two/repo").unwrap(),
-        }));
+        assert!(
+            !filter.filter(&SourceRepositoryMapping {
+                path: PathBuf::new(),
+                ref_match: None,
+                ref_rewrites: None,
+                git_url: url::Url::parse("https://github.com/two/repo")
+                    .unwrap()
+                    .into(),
+                metadata: Default::default(),
+            })
+        );
 
-        assert!(filter.filter(&SourceRepositoryMapping {
-            path: PathBuf::new(),
-            ref_match: None,
-            git_url: url::Url::parse("https://github.com/three/repo").unwrap(),
-        }));
+        assert!(
+            filter.filter(&SourceRepositoryMapping {
+                path: PathBuf::new(),
+                ref_match: None,
+                ref_rewrites: None,
+                git_url: url::Url::parse("https://github.com/three/repo")
+                    .unwrap()
+                    .into(),
+                metadata: Default::default(),
+            })
+        );
     }
 }
@@ -0,0 +1,57 @@
+use super::{OpenRequest, repository_path};
+use anyhow::Result;
+use octocrab::{Octocrab, params::State};
+use std::collections::HashMap;
+use url::Url;
+
+pub(super) async fn open_requests(
+    url: &Url,
+    token: Option<String>,
+) -> Result<HashMap<String, OpenRequest>> {
+    let (owner, name) = repository_path(url)?;
+
+    let mut builder = Octocrab::builder();
+    if let Some(token) = token {
+        builder = builder.personal_token(token);
+    }
+    // GitHub Enterprise Server hosts its API under the same host
+    if let Some(host) = url.host_str()
+        && host != "github.com"
+    {
+        builder = builder.base_uri(format!("https://{}/api/v3", host))?;
+    }
+    let octocrab = builder.build()?;
+
+    let mut page = octocrab
+        .pulls(&owner, &name)
+        .list()
+        .state(State::Open)
+        .per_page(100)
+        .send()
+        .await?;
+
+    let mut pulls = page.take_items();
+
+    while let Some(mut new_page) = octocrab.get_page(&page.next).await? {
+        pulls.extend(new_page.take_items());
+        page = new_page;
+    }
+
+    Ok(pulls
+        .into_iter()
+        .map(|p| {
+            (
+                format!("refs/pull/{}/head", p.number),
+                OpenRequest {
+                    author: p.user.map(|u| u.login),
+                    labels: p
+                        .labels
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|l| l.name)
+                        .collect(),
+                },
+            )
+        })
+        .collect())
+}
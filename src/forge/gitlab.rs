@@ -0,0 +1,73 @@
+use super::{OpenRequest, repository_path};
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::HashMap;
+use url::Url;
+
+const PER_PAGE: usize = 100;
+
+#[derive(Debug, Deserialize)]
+struct MergeRequest {
+    iid: u64,
+    author: Option<User>,
+    #[serde(default)]
+    labels: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct User {
+    username: String,
+}
+
+pub(super) async fn open_requests(
+    url: &Url,
+    token: Option<String>,
+) -> Result<HashMap<String, OpenRequest>> {
+    let (namespace, name) = repository_path(url)?;
+    let project = format!("{}/{}", namespace, name);
+
+    let mut api = Url::parse(&format!(
+        "https://{}/api/v4/projects/",
+        url.host_str().unwrap_or_default()
+    ))?;
+    // The project path must be a single (encoded) path segment
+    api.path_segments_mut()
+        .unwrap()
+        .pop_if_empty()
+        .push(&project)
+        .push("merge_requests");
+
+    let client = reqwest::Client::new();
+    let mut requests = Vec::new();
+
+    for page in 1.. {
+        let mut request = client.get(api.clone()).query(&[
+            ("state", "opened".to_string()),
+            ("per_page", PER_PAGE.to_string()),
+            ("page", page.to_string()),
+        ]);
+        if let Some(token) = &token {
+            request = request.header("PRIVATE-TOKEN", token);
+        }
+
+        let page: Vec<MergeRequest> = request.send().await?.error_for_status()?.json().await?;
+        let last = page.len() < PER_PAGE;
+        requests.extend(page);
+        if last {
+            break;
+        }
+    }
+
+    Ok(requests
+        .into_iter()
+        .map(|mr| {
+            (
+                format!("refs/merge-requests/{}/head", mr.iid),
+                OpenRequest {
+                    author: mr.author.map(|a| a.username),
+                    labels: mr.labels,
+                },
+            )
+        })
+        .collect())
+}
@@ -0,0 +1,98 @@
+mod github;
+mod gitlab;
+
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use std::collections::HashMap;
+use url::Url;
+
+/// A code hosting service that can be queried for information about a repository.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Forge {
+    Github,
+    Gitlab,
+}
+
+impl Forge {
+    /// Guesses the forge from well known host names.
+    pub(crate) fn detect(url: &Url) -> Option<Self> {
+        match url.host_str()? {
+            "github.com" => Some(Forge::Github),
+            h if h == "gitlab.com" || h.starts_with("gitlab.") => Some(Forge::Gitlab),
+            _ => None,
+        }
+    }
+
+    /// Returns the currently open pull/merge requests, keyed by the name of the ref that holds the
+    /// head of each request.
+    pub(crate) async fn open_requests(
+        &self,
+        url: &Url,
+        token: Option<String>,
+    ) -> Result<HashMap<String, OpenRequest>> {
+        match self {
+            Forge::Github => github::open_requests(url, token).await,
+            Forge::Gitlab => gitlab::open_requests(url, token).await,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct OpenRequest {
+    pub author: Option<String>,
+    pub labels: Vec<String>,
+}
+
+/// Splits a repository URL path into the namespace/owner and the repository name.
+fn repository_path(url: &Url) -> Result<(String, String)> {
+    let path = url.path().trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+
+    match path.rsplit_once('/') {
+        Some((owner, name)) if !owner.is_empty() && !name.is_empty() => {
+            Ok((owner.to_string(), name.to_string()))
+        }
+        _ => Err(anyhow!(
+            "Cannot determine repository from URL path {}",
+            url.path()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect() {
+        let d = |u: &str| Forge::detect(&Url::parse(u).unwrap());
+        assert_eq!(
+            d("https://github.com/DanNixon/git-collage"),
+            Some(Forge::Github)
+        );
+        assert_eq!(
+            d("https://gitlab.com/group/project.git"),
+            Some(Forge::Gitlab)
+        );
+        assert_eq!(
+            d("https://gitlab.example.com/group/project"),
+            Some(Forge::Gitlab)
+        );
+        assert_eq!(d("https://git.example.com/group/project"), None);
+    }
+
+    #[test]
+    fn path() {
+        let p = |u: &str| repository_path(&Url::parse(u).unwrap()).ok();
+        assert_eq!(
+            p("https://github.com/DanNixon/git-collage.git"),
+            Some(("DanNixon".to_string(), "git-collage".to_string()))
+        );
+        assert_eq!(
+            p("https://gitlab.com/group/subgroup/project/"),
+            Some(("group/subgroup".to_string(), "project".to_string()))
+        );
+        assert_eq!(p("https://github.com/nope"), None);
+    }
+}
@@ -1,7 +1,17 @@
+mod auth;
 mod config;
+mod credentials;
+mod deleted_refs;
 mod filter;
+mod forge;
 mod matching_rules;
 mod operation;
+mod ref_rewrite;
+mod remote_address;
+mod retry;
+mod scheduler;
+mod script;
+mod snapshot;
 mod source;
 mod util;
 
@@ -22,22 +32,40 @@ struct Cli {
     #[clap(short, long, value_name = "FILE|DIR")]
     config: Vec<PathBuf>,
 
+    #[clap(flatten)]
+    limits: scheduler::Limits,
+
+    #[clap(flatten)]
+    failure_policy: operation::FailurePolicy,
+
     #[clap(subcommand)]
     command: operation::Command,
 }
 
+fn main() -> Result<()> {
+    // SAFETY: no other threads have been started yet
+    unsafe { retry::set_network_timeouts()? };
+    run()
+}
+
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn run() -> Result<()> {
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
     CompleteEnv::with_factory(Cli::command).complete();
     let cli = Cli::parse();
 
-    let config = Config::load(&cli.config)?;
-    trace!("Config = {:#?}", config);
-
-    let mappings = config.repository_mappings().await;
+    // Commands working on local mirrors alone do not need any configuration
+    let mappings = if cli.command.needs_discovery() {
+        let config = Config::load(&cli.config)?;
+        trace!("Config = {:#?}", config);
+        config.repository_mappings().await
+    } else {
+        Vec::new()
+    };
     trace!("Repository mappings = {:#?}", mappings);
 
-    cli.command.run(mappings)
+    cli.command
+        .run(mappings, &cli.limits, &cli.failure_policy)
+        .await
 }
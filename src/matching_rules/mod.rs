@@ -0,0 +1,879 @@
+// TODO: tidy this module
+
+mod open_requests;
+pub(crate) mod presets;
+mod semver;
+
+use crate::script::Script;
+use anyhow::Result;
+use chrono::{TimeDelta, Utc};
+use git2::{Oid, RemoteHead};
+use globset::{GlobBuilder, GlobMatcher};
+use regex::Regex;
+use rhai::Scope;
+use serde::{
+    Deserialize, Deserializer,
+    de::{MapAccess, Visitor, value::MapAccessDeserializer},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+use url::Url;
+
+pub(crate) trait Match {
+    fn matches(&self, name: &str, target: Oid) -> bool;
+}
+
+/// Something that can be selected by a [`Ruleset`].
+pub(crate) trait Ref {
+    fn name(&self) -> &str;
+    fn target(&self) -> Oid;
+}
+
+impl<T: Ref> Ref for &T {
+    fn name(&self) -> &str {
+        (*self).name()
+    }
+
+    fn target(&self) -> Oid {
+        (*self).target()
+    }
+}
+
+impl Ref for RemoteHead<'_> {
+    fn name(&self) -> &str {
+        self.name()
+    }
+
+    fn target(&self) -> Oid {
+        self.oid()
+    }
+}
+
+impl Ref for (&str, Oid) {
+    fn name(&self) -> &str {
+        self.0
+    }
+
+    fn target(&self) -> Oid {
+        self.1
+    }
+}
+
+/// Information about refs that is not known from their name and target alone, required by some
+/// rules.
+#[derive(Debug, Default)]
+pub(crate) struct Context {
+    /// Commit time (seconds since the UNIX epoch) of the commit each ref target points at
+    pub commit_times: HashMap<Oid, i64>,
+    /// Full name of the branch the remote's `HEAD` points at
+    pub default_branch: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Ruleset {
+    mode: Mode,
+    rules: Vec<RulesetEntry>,
+}
+
+impl Ruleset {
+    /// Returns the refs that are selected by this ruleset.
+    ///
+    /// Some rules (e.g. selecting the latest N versions) depend on the full set of candidate refs,
+    /// so all refs under consideration should be passed together.
+    pub(crate) fn select<R: Ref>(
+        &self,
+        refs: impl IntoIterator<Item = R>,
+        ctx: &Context,
+    ) -> Vec<R> {
+        let refs: Vec<R> = refs.into_iter().collect();
+        let selected = self.selected_names(&refs, ctx, false);
+        refs.into_iter()
+            .filter(|r| selected.contains(r.name()))
+            .collect()
+    }
+
+    /// Queries any external information the rules need for the given repository.
+    pub(crate) async fn resolve(&mut self, url: &Url) -> Result<()> {
+        for e in &mut self.rules {
+            if let Rule::OpenRequests(r) = &mut e.rule {
+                r.resolve(url).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true if any rule needs [`Ruleset::resolve`] to be called before it can match.
+    pub(crate) fn needs_resolving(&self) -> bool {
+        self.rules
+            .iter()
+            .any(|e| matches!(e.rule, Rule::OpenRequests(_)))
+    }
+
+    /// Returns true if any rule depends on [`Context::commit_times`].
+    pub(crate) fn uses_commit_times(&self) -> bool {
+        self.rules
+            .iter()
+            .any(|e| matches!(e.rule, Rule::CommittedWithinDays(_)))
+    }
+
+    /// Returns the refs that could be selected once their commit times are known, i.e. those for
+    /// which commit times should be obtained before calling [`Ruleset::select`].
+    pub(crate) fn candidates<R: Ref>(&self, refs: impl IntoIterator<Item = R>) -> Vec<R> {
+        let refs: Vec<R> = refs.into_iter().collect();
+        let ctx = Context::default();
+        let mut selected = self.selected_names(&refs, &ctx, false);
+        selected.extend(self.selected_names(&refs, &ctx, true));
+        refs.into_iter()
+            .filter(|r| selected.contains(r.name()))
+            .collect()
+    }
+
+    fn selected_names<R: Ref>(
+        &self,
+        refs: &[R],
+        ctx: &Context,
+        unknown_commit_time_matches: bool,
+    ) -> HashSet<String> {
+        let now = Utc::now();
+
+        let selections: Vec<Option<HashSet<String>>> = self
+            .rules
+            .iter()
+            .map(|e| match &e.rule {
+                Rule::Semver(r) => Some(
+                    r.select(refs.iter().map(|r| r.name()))
+                        .into_iter()
+                        .map(String::from)
+                        .collect(),
+                ),
+                _ => None,
+            })
+            .collect();
+
+        refs.iter()
+            .filter(|r| {
+                self.included(|i, e| match (&selections[i], &e.rule) {
+                    (Some(selection), _) => selection.contains(r.name()),
+                    (None, Rule::CommittedWithinDays(days)) => {
+                        match ctx.commit_times.get(&r.target()) {
+                            Some(t) => *t >= (now - TimeDelta::days((*days).into())).timestamp(),
+                            None => unknown_commit_time_matches,
+                        }
+                    }
+                    (None, Rule::DefaultBranch) => ctx.default_branch.as_deref() == Some(r.name()),
+                    (None, rule) => rule.matches(r.name(), r.target()),
+                })
+            })
+            .map(|r| r.name().to_string())
+            .collect()
+    }
+
+    fn included(&self, matches: impl Fn(usize, &RulesetEntry) -> bool) -> bool {
+        let mut matching = self
+            .rules
+            .iter()
+            .enumerate()
+            .filter(|(i, e)| matches(*i, e))
+            .map(|(_, e)| &e.action);
+
+        match self.mode {
+            Mode::Any => {
+                let mut included = false;
+                for action in matching {
+                    match action {
+                        Action::Include => included = true,
+                        Action::Exclude => return false,
+                    }
+                }
+                included
+            }
+            Mode::FirstMatch => matching.next() == Some(&Action::Include),
+        }
+    }
+}
+
+impl Match for Ruleset {
+    fn matches(&self, name: &str, target: Oid) -> bool {
+        !self
+            .select([(name, target)], &Context::default())
+            .is_empty()
+    }
+}
+
+/// A ruleset is either given inline as a table, or by the name of a preset (see [`presets`]).
+impl<'de> Deserialize<'de> for Ruleset {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(RulesetVisitor)
+    }
+}
+
+#[derive(Deserialize)]
+struct RulesetTable {
+    #[serde(default)]
+    mode: Mode,
+    rules: Vec<RulesetEntry>,
+}
+
+struct RulesetVisitor;
+
+impl<'de> Visitor<'de> for RulesetVisitor {
+    type Value = Ruleset;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "a table containing `rules` and optionally `mode`, or the name of a preset"
+        )
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        presets::get(v).map_err(serde::de::Error::custom)
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let RulesetTable { mode, rules } =
+            RulesetTable::deserialize(MapAccessDeserializer::new(map))?;
+        Ok(Ruleset { mode, rules })
+    }
+}
+
+/// How the rules in a [`Ruleset`] are combined.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Mode {
+    /// A ref matches if any include rule matches it and no exclude rule matches it.
+    #[default]
+    Any,
+    /// Rules are evaluated in order and the first rule to match a ref decides if it is included.
+    /// Refs matching no rule are not included.
+    FirstMatch,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Action {
+    #[default]
+    Include,
+    Exclude,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct RulesetEntry {
+    #[serde(default)]
+    action: Action,
+    #[serde(flatten)]
+    rule: Rule,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", content = "expr", rename_all = "snake_case")]
+enum Rule {
+    Exact(String),
+    Glob(GlobRule),
+    Regex(RegexRule),
+    Script(Script),
+    Semver(semver::SemverRule),
+    /// Matches refs pointing at a commit made within the given number of days
+    CommittedWithinDays(u32),
+    /// Matches the branch the remote's `HEAD` points at
+    DefaultBranch,
+    OpenRequests(open_requests::OpenRequestsRule),
+}
+
+impl Match for Rule {
+    fn matches(&self, name: &str, target: Oid) -> bool {
+        match &self {
+            Rule::Exact(s) => s == name,
+            Rule::Glob(g) => g.matches(name, target),
+            Rule::Regex(r) => r.matches(name, target),
+            Rule::Script(s) => s.matches(name, target),
+            Rule::Semver(s) => !s.select([name]).is_empty(),
+            // Requires commit times, see `Ruleset::select`
+            Rule::CommittedWithinDays(_) => false,
+            // Requires the remote default branch, see `Ruleset::select`
+            Rule::DefaultBranch => false,
+            Rule::OpenRequests(r) => r.matches(name),
+        }
+    }
+}
+
+/// Matches ref names using git style wildcards, i.e. `*` matches within a single path component
+/// and `**` matches across any number of components.
+#[derive(Clone, Debug)]
+struct GlobRule {
+    glob: GlobMatcher,
+}
+
+impl GlobRule {
+    fn new(pattern: &str) -> Result<Self, globset::Error> {
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .backslash_escape(true)
+            .build()?
+            .compile_matcher();
+        Ok(Self { glob })
+    }
+}
+
+impl Match for GlobRule {
+    fn matches(&self, name: &str, _: Oid) -> bool {
+        self.glob.is_match(name)
+    }
+}
+
+impl<'de> Deserialize<'de> for GlobRule {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(GlobRuleVisitor)
+    }
+}
+
+struct GlobRuleVisitor;
+
+impl Visitor<'_> for GlobRuleVisitor {
+    type Value = GlobRule;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a valid glob string")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        GlobRule::new(v)
+            .map_err(|e| serde::de::Error::custom(format!("failed to parse glob: {}", e)))
+    }
+}
+
+/// Matches ref names against a regular expression.
+/// By default the expression may match any part of the name, if `anchored` it must match all of it.
+#[derive(Clone, Debug)]
+struct RegexRule {
+    regex: Regex,
+}
+
+impl RegexRule {
+    fn new(pattern: &str, anchored: bool) -> Result<Self, regex::Error> {
+        let regex = if anchored {
+            Regex::new(&format!("^(?:{})$", pattern))?
+        } else {
+            Regex::new(pattern)?
+        };
+        Ok(Self { regex })
+    }
+}
+
+impl Match for RegexRule {
+    fn matches(&self, name: &str, _: Oid) -> bool {
+        self.regex.is_match(name)
+    }
+}
+
+impl<'de> Deserialize<'de> for RegexRule {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(RegexRuleVisitor)
+    }
+}
+
+struct RegexRuleVisitor;
+
+impl<'de> Visitor<'de> for RegexRuleVisitor {
+    type Value = RegexRule;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "a valid regex string or a table containing `pattern` and optionally `anchored`"
+        )
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        RegexRule::new(v, false)
+            .map_err(|e| serde::de::Error::custom(format!("failed to parse regex: {}", e)))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut pattern: Option<String> = None;
+        let mut anchored = false;
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "pattern" => pattern = Some(map.next_value()?),
+                "anchored" => anchored = map.next_value()?,
+                k => {
+                    return Err(serde::de::Error::unknown_field(k, &["pattern", "anchored"]));
+                }
+            }
+        }
+
+        let pattern = pattern.ok_or_else(|| serde::de::Error::missing_field("pattern"))?;
+        RegexRule::new(&pattern, anchored)
+            .map_err(|e| serde::de::Error::custom(format!("failed to parse regex: {}", e)))
+    }
+}
+
+/// Scripts have access to the ref `name` and the hex ID of the object it `target`s.
+/// Refs for which the script fails are not matched.
+impl Match for Script {
+    fn matches(&self, name: &str, target: Oid) -> bool {
+        let mut scope = Scope::new();
+        scope
+            .push("name", name.to_string())
+            .push("target", target.to_string());
+
+        match self.eval(&mut scope) {
+            Ok(v) => v,
+            Err(e) => {
+                log::error!("Ref matching script failed for {}: {}", name, e);
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn include(rule: Rule) -> RulesetEntry {
+        RulesetEntry {
+            action: Action::Include,
+            rule,
+        }
+    }
+
+    fn exclude(rule: Rule) -> RulesetEntry {
+        RulesetEntry {
+            action: Action::Exclude,
+            rule,
+        }
+    }
+
+    fn regex(r: &str) -> Rule {
+        Rule::Regex(RegexRule {
+            regex: Regex::new(r).unwrap(),
+        })
+    }
+
+    #[test]
+    fn ruleset() {
+        let rs = Ruleset {
+            mode: Mode::Any,
+            rules: vec![
+                include(Rule::Exact("refs/heads/main".to_string())),
+                include(Rule::Regex(RegexRule {
+                    regex: Regex::new("refs/tags/.*").unwrap(),
+                })),
+            ],
+        };
+        assert!(rs.matches("refs/heads/main", Oid::zero()));
+        assert!(!rs.matches("refs/heads/develop", Oid::zero()));
+        assert!(rs.matches("refs/tags/v0.1.0", Oid::zero()));
+        assert!(rs.matches("refs/tags/v0.1.1", Oid::zero()));
+    }
+
+    #[test]
+    fn ruleset_exclude() {
+        let rs = Ruleset {
+            mode: Mode::Any,
+            rules: vec![
+                exclude(regex("^refs/heads/dependabot/")),
+                include(regex("^refs/heads/")),
+                exclude(regex("^refs/heads/renovate/")),
+            ],
+        };
+        assert!(rs.matches("refs/heads/main", Oid::zero()));
+        assert!(rs.matches("refs/heads/feature/dependabot", Oid::zero()));
+        assert!(!rs.matches("refs/heads/dependabot/cargo/foo", Oid::zero()));
+        assert!(!rs.matches("refs/heads/renovate/foo", Oid::zero()));
+        assert!(!rs.matches("refs/tags/v0.1.0", Oid::zero()));
+    }
+
+    #[test]
+    fn ruleset_only_exclude() {
+        let rs = Ruleset {
+            mode: Mode::Any,
+            rules: vec![exclude(regex("^refs/heads/"))],
+        };
+        assert!(!rs.matches("refs/heads/main", Oid::zero()));
+        assert!(!rs.matches("refs/tags/v0.1.0", Oid::zero()));
+    }
+
+    #[test]
+    fn ruleset_first_match() {
+        let rs = Ruleset {
+            mode: Mode::FirstMatch,
+            rules: vec![
+                include(Rule::Exact("refs/heads/dependabot/keep".to_string())),
+                exclude(regex("^refs/heads/dependabot/")),
+                include(regex("^refs/heads/")),
+            ],
+        };
+        assert!(rs.matches("refs/heads/main", Oid::zero()));
+        assert!(rs.matches("refs/heads/dependabot/keep", Oid::zero()));
+        assert!(!rs.matches("refs/heads/dependabot/cargo/foo", Oid::zero()));
+        assert!(!rs.matches("refs/tags/v0.1.0", Oid::zero()));
+    }
+
+    #[test]
+    fn ruleset_deserialize() {
+        let rs: Ruleset = toml::from_str(
+            r#"
+            mode = 'first_match'
+
+            [[rules]]
+            action = 'exclude'
+            type = 'regex'
+            expr = '^refs/heads/dependabot/'
+
+            [[rules]]
+            type = 'regex'
+            expr = '^refs/heads/'
+            "#,
+        )
+        .unwrap();
+        assert!(rs.matches("refs/heads/main", Oid::zero()));
+        assert!(!rs.matches("refs/heads/dependabot/cargo/foo", Oid::zero()));
+
+        let rs: Ruleset = toml::from_str(
+            r#"
+            [[rules]]
+            type = 'exact'
+            expr = 'refs/heads/main'
+            "#,
+        )
+        .unwrap();
+        assert!(rs.matches("refs/heads/main", Oid::zero()));
+        assert!(!rs.matches("refs/heads/develop", Oid::zero()));
+    }
+
+    #[test]
+    fn ruleset_select_semver() {
+        let rs: Ruleset = toml::from_str(
+            r#"
+            [[rules]]
+            type = 'exact'
+            expr = 'refs/heads/main'
+            [[rules]]
+            type = 'semver'
+            expr = { prefix = 'v', latest = 2 }
+            [[rules]]
+            action = 'exclude'
+            type = 'exact'
+            expr = 'refs/tags/v1.2.0'
+            "#,
+        )
+        .unwrap();
+
+        let refs = [
+            ("refs/heads/main", Oid::zero()),
+            ("refs/heads/develop", Oid::zero()),
+            ("refs/tags/v1.0.0", Oid::zero()),
+            ("refs/tags/v1.1.0", Oid::zero()),
+            ("refs/tags/v1.2.0", Oid::zero()),
+        ];
+        let selected: Vec<&str> = rs
+            .select(refs, &Context::default())
+            .into_iter()
+            .map(|r| r.0)
+            .collect();
+        assert_eq!(selected, vec!["refs/heads/main", "refs/tags/v1.1.0"]);
+
+        // Selection is relative to the refs under consideration
+        assert!(rs.matches("refs/tags/v1.0.0", Oid::zero()));
+    }
+
+    #[test]
+    fn ruleset_committed_within_days() {
+        let rs: Ruleset = toml::from_str(
+            r#"
+            [[rules]]
+            type = 'exact'
+            expr = 'refs/heads/main'
+            [[rules]]
+            type = 'committed_within_days'
+            expr = 90
+            [[rules]]
+            action = 'exclude'
+            type = 'glob'
+            expr = 'refs/tags/*'
+            "#,
+        )
+        .unwrap();
+        assert!(rs.uses_commit_times());
+
+        let active = Oid::from_str("1111111111111111111111111111111111111111").unwrap();
+        let stale = Oid::from_str("2222222222222222222222222222222222222222").unwrap();
+        let unknown = Oid::from_str("3333333333333333333333333333333333333333").unwrap();
+
+        let refs = [
+            ("refs/heads/main", stale),
+            ("refs/heads/active", active),
+            ("refs/heads/stale", stale),
+            ("refs/heads/unknown", unknown),
+            ("refs/tags/active", active),
+        ];
+
+        let candidates: Vec<&str> = rs.candidates(refs).into_iter().map(|r| r.0).collect();
+        assert_eq!(
+            candidates,
+            vec![
+                "refs/heads/main",
+                "refs/heads/active",
+                "refs/heads/stale",
+                "refs/heads/unknown"
+            ]
+        );
+
+        let ctx = Context {
+            commit_times: HashMap::from([
+                (active, (Utc::now() - TimeDelta::days(10)).timestamp()),
+                (stale, (Utc::now() - TimeDelta::days(100)).timestamp()),
+            ]),
+            ..Default::default()
+        };
+        let selected: Vec<&str> = rs.select(refs, &ctx).into_iter().map(|r| r.0).collect();
+        assert_eq!(selected, vec!["refs/heads/main", "refs/heads/active"]);
+    }
+
+    #[test]
+    fn ruleset_exclude_committed_within_days() {
+        let rs: Ruleset = toml::from_str(
+            r#"
+            [[rules]]
+            type = 'glob'
+            expr = 'refs/heads/*'
+            [[rules]]
+            action = 'exclude'
+            type = 'committed_within_days'
+            expr = 90
+            "#,
+        )
+        .unwrap();
+
+        let active = Oid::from_str("1111111111111111111111111111111111111111").unwrap();
+        let stale = Oid::from_str("2222222222222222222222222222222222222222").unwrap();
+
+        let refs = [("refs/heads/active", active), ("refs/heads/stale", stale)];
+        assert_eq!(rs.candidates(refs).len(), 2);
+
+        let ctx = Context {
+            commit_times: HashMap::from([
+                (active, (Utc::now() - TimeDelta::days(10)).timestamp()),
+                (stale, (Utc::now() - TimeDelta::days(100)).timestamp()),
+            ]),
+            ..Default::default()
+        };
+        let selected: Vec<&str> = rs.select(refs, &ctx).into_iter().map(|r| r.0).collect();
+        assert_eq!(selected, vec!["refs/heads/stale"]);
+    }
+
+    #[test]
+    fn ruleset_default_branch() {
+        let rs: Ruleset = toml::from_str(
+            r#"
+            [[rules]]
+            type = 'default_branch'
+            [[rules]]
+            type = 'glob'
+            expr = 'refs/tags/*'
+            "#,
+        )
+        .unwrap();
+
+        let refs = [
+            ("refs/heads/main", Oid::zero()),
+            ("refs/heads/trunk", Oid::zero()),
+            ("refs/tags/v1.0.0", Oid::zero()),
+        ];
+
+        let ctx = Context {
+            default_branch: Some("refs/heads/trunk".to_string()),
+            ..Default::default()
+        };
+        let selected: Vec<&str> = rs.select(refs, &ctx).into_iter().map(|r| r.0).collect();
+        assert_eq!(selected, vec!["refs/heads/trunk", "refs/tags/v1.0.0"]);
+
+        let selected: Vec<&str> = rs
+            .select(refs, &Context::default())
+            .into_iter()
+            .map(|r| r.0)
+            .collect();
+        assert_eq!(selected, vec!["refs/tags/v1.0.0"]);
+    }
+
+    #[test]
+    fn rule_exact() {
+        let r = Rule::Exact("refs/heads/main".to_string());
+        assert!(r.matches("refs/heads/main", Oid::zero()));
+        assert!(!r.matches("refs/heads/develop", Oid::zero()));
+    }
+
+    #[test]
+    fn rule_regex() {
+        let r = Rule::Regex(RegexRule {
+            regex: Regex::new("refs/heads/ma.*").unwrap(),
+        });
+        assert!(r.matches("refs/heads/main", Oid::zero()));
+        assert!(r.matches("refs/heads/master", Oid::zero()));
+        assert!(!r.matches("refs/heads/develop", Oid::zero()));
+
+        let r = Rule::Regex(RegexRule {
+            regex: Regex::new("refs/tags/.*").unwrap(),
+        });
+        assert!(r.matches("refs/tags/v0.1.0", Oid::zero()));
+        assert!(r.matches("refs/tags/v0.1.1", Oid::zero()));
+        assert!(!r.matches("refs/heads/develop", Oid::zero()));
+
+        // Unanchored expressions match anywhere in the name
+        assert!(r.matches("refs/heads/refs/tags/foo", Oid::zero()));
+    }
+
+    #[test]
+    fn rule_regex_anchored() {
+        let r = Rule::Regex(RegexRule::new("refs/tags/.*", true).unwrap());
+        assert!(r.matches("refs/tags/v0.1.0", Oid::zero()));
+        assert!(!r.matches("refs/heads/refs/tags/foo", Oid::zero()));
+
+        let r = Rule::Regex(RegexRule::new("refs/heads/main|refs/heads/master", true).unwrap());
+        assert!(r.matches("refs/heads/main", Oid::zero()));
+        assert!(r.matches("refs/heads/master", Oid::zero()));
+        assert!(!r.matches("refs/heads/main-old", Oid::zero()));
+        assert!(!r.matches("refs/heads/old/refs/heads/master", Oid::zero()));
+    }
+
+    #[test]
+    fn rule_regex_deserialize() {
+        let r: Rule = toml::from_str(
+            r#"
+            type = 'regex'
+            expr = 'refs/tags/.*'
+            "#,
+        )
+        .unwrap();
+        assert!(r.matches("refs/heads/refs/tags/foo", Oid::zero()));
+
+        let r: Rule = toml::from_str(
+            r#"
+            type = 'regex'
+            expr = { pattern = 'refs/tags/.*', anchored = true }
+            "#,
+        )
+        .unwrap();
+        assert!(r.matches("refs/tags/v0.1.0", Oid::zero()));
+        assert!(!r.matches("refs/heads/refs/tags/foo", Oid::zero()));
+
+        let rs: Ruleset = toml::from_str(
+            r#"
+            [[rules]]
+            action = 'exclude'
+            type = 'regex'
+            expr = { pattern = 'refs/heads/wip.*', anchored = true }
+            [[rules]]
+            type = 'glob'
+            expr = 'refs/heads/**'
+            "#,
+        )
+        .unwrap();
+        assert!(rs.matches("refs/heads/feature/wip", Oid::zero()));
+        assert!(!rs.matches("refs/heads/wip-thing", Oid::zero()));
+
+        assert!(
+            toml::from_str::<Rule>(
+                r#"
+                type = 'regex'
+                expr = { pattern = 'refs/tags/.*', anchor = true }
+                "#,
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn rule_glob() {
+        let r = Rule::Glob(GlobRule::new("refs/heads/*").unwrap());
+        assert!(r.matches("refs/heads/main", Oid::zero()));
+        assert!(!r.matches("refs/heads/feature/foo", Oid::zero()));
+        assert!(!r.matches("refs/tags/v0.1.0", Oid::zero()));
+        assert!(!r.matches("refs/remotes/origin/refs/heads/main", Oid::zero()));
+
+        let r = Rule::Glob(GlobRule::new("refs/heads/**").unwrap());
+        assert!(r.matches("refs/heads/main", Oid::zero()));
+        assert!(r.matches("refs/heads/feature/foo", Oid::zero()));
+
+        let r = Rule::Glob(GlobRule::new("refs/**/v*").unwrap());
+        assert!(r.matches("refs/v1", Oid::zero()));
+        assert!(r.matches("refs/tags/v0.1.0", Oid::zero()));
+        assert!(r.matches("refs/tags/releases/v0.1.0", Oid::zero()));
+        assert!(!r.matches("refs/tags/0.1.0", Oid::zero()));
+    }
+
+    #[test]
+    fn rule_glob_deserialize() {
+        let r: Rule = toml::from_str(
+            r#"
+            type = 'glob'
+            expr = 'refs/pull/*/head'
+            "#,
+        )
+        .unwrap();
+        assert!(r.matches("refs/pull/42/head", Oid::zero()));
+        assert!(!r.matches("refs/pull/42/merge", Oid::zero()));
+
+        assert!(
+            toml::from_str::<Rule>(
+                r#"
+                type = 'glob'
+                expr = 'refs/heads/[main'
+                "#,
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn rule_script() {
+        let r: Rule = toml::from_str(
+            r#"
+            type = 'script'
+            expr = 'name.starts_with("refs/heads/") && !name.contains("dependabot") && target != "0000000000000000000000000000000000000000"'
+            "#,
+        )
+        .unwrap();
+
+        let oid = Oid::from_str("8d2f4ab1e0a3b5c7d9e1f2a3b4c5d6e7f8091a2b").unwrap();
+        assert!(r.matches("refs/heads/main", oid));
+        assert!(!r.matches("refs/heads/dependabot/cargo/foo", oid));
+        assert!(!r.matches("refs/tags/v0.1.0", oid));
+        assert!(!r.matches("refs/heads/main", Oid::zero()));
+    }
+
+    #[test]
+    fn rule_script_error_does_not_match() {
+        let r = Rule::Script(Script::new("name.len() > nope").unwrap());
+        assert!(!r.matches("refs/heads/main", Oid::zero()));
+    }
+}
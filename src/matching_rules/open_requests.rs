@@ -0,0 +1,122 @@
+use crate::{
+    credentials::CredentialSource,
+    forge::{Forge, OpenRequest},
+};
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use std::collections::HashMap;
+use url::Url;
+
+/// Selects the head refs of pull/merge requests that are currently open, as reported by the forge
+/// hosting the repository.
+/// Must be resolved (see [`OpenRequestsRule::resolve`]) before any refs are matched.
+#[derive(Clone, Debug, Deserialize)]
+pub(super) struct OpenRequestsRule {
+    /// Forge to query, detected from the repository URL if not given
+    forge: Option<Forge>,
+    /// API token for the forge
+    #[serde(alias = "token_env_var")]
+    token: Option<CredentialSource>,
+    /// Only select requests with at least one of these labels
+    #[serde(default)]
+    labels: Vec<String>,
+    /// Only select requests opened by one of these users
+    #[serde(default)]
+    authors: Vec<String>,
+
+    #[serde(skip)]
+    open: Option<HashMap<String, OpenRequest>>,
+}
+
+impl OpenRequestsRule {
+    pub(super) async fn resolve(&mut self, url: &Url) -> Result<()> {
+        let forge = self
+            .forge
+            .or_else(|| Forge::detect(url))
+            .ok_or_else(|| anyhow!("Cannot determine forge for {}", url.path()))?;
+
+        let token = match &self.token {
+            Some(t) => Some(t.get(url)?.secret),
+            None => None,
+        };
+
+        self.open = Some(forge.open_requests(url, token).await?);
+        Ok(())
+    }
+
+    pub(super) fn matches(&self, name: &str) -> bool {
+        self.open
+            .as_ref()
+            .and_then(|o| o.get(name))
+            .is_some_and(|r| {
+                (self.labels.is_empty() || r.labels.iter().any(|l| self.labels.contains(l)))
+                    && (self.authors.is_empty()
+                        || r.author.as_ref().is_some_and(|a| self.authors.contains(a)))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(toml: &str) -> OpenRequestsRule {
+        let mut r: OpenRequestsRule = toml::from_str(toml).unwrap();
+        r.open = Some(HashMap::from([
+            (
+                "refs/pull/1/head".to_string(),
+                OpenRequest {
+                    author: Some("alice".to_string()),
+                    labels: vec!["review".to_string()],
+                },
+            ),
+            (
+                "refs/pull/2/head".to_string(),
+                OpenRequest {
+                    author: Some("bob".to_string()),
+                    labels: vec![],
+                },
+            ),
+        ]));
+        r
+    }
+
+    #[test]
+    fn unresolved() {
+        let r: OpenRequestsRule = toml::from_str("").unwrap();
+        assert!(!r.matches("refs/pull/1/head"));
+    }
+
+    #[test]
+    fn all_open() {
+        let r = rule("");
+        assert!(r.matches("refs/pull/1/head"));
+        assert!(r.matches("refs/pull/2/head"));
+        assert!(!r.matches("refs/pull/3/head"));
+        assert!(!r.matches("refs/pull/1/merge"));
+    }
+
+    #[test]
+    fn labels() {
+        let r = rule("labels = ['review', 'urgent']");
+        assert!(r.matches("refs/pull/1/head"));
+        assert!(!r.matches("refs/pull/2/head"));
+    }
+
+    #[test]
+    fn authors() {
+        let r = rule("authors = ['bob']");
+        assert!(!r.matches("refs/pull/1/head"));
+        assert!(r.matches("refs/pull/2/head"));
+    }
+
+    #[test]
+    fn unknown_forge() {
+        let mut r: OpenRequestsRule = toml::from_str("").unwrap();
+        let url = Url::parse("https://git.example.com/group/project").unwrap();
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        assert!(rt.block_on(r.resolve(&url)).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+use super::Ruleset;
+use anyhow::{Result, anyhow};
+use std::{cell::RefCell, collections::HashMap};
+
+const BUILTIN: &[(&str, &str)] = &[
+    (
+        "default-branch-and-tags",
+        r#"
+        [[rules]]
+        type = 'default_branch'
+        [[rules]]
+        type = 'glob'
+        expr = 'refs/tags/**'
+        "#,
+    ),
+    (
+        "all-branches-and-tags",
+        r#"
+        [[rules]]
+        type = 'glob'
+        expr = 'refs/heads/**'
+        [[rules]]
+        type = 'glob'
+        expr = 'refs/tags/**'
+        "#,
+    ),
+    (
+        "releases-only",
+        r#"
+        [[rules]]
+        type = 'semver'
+        expr = { exclude_prereleases = true }
+        [[rules]]
+        type = 'semver'
+        expr = { prefix = 'v', exclude_prereleases = true }
+        "#,
+    ),
+];
+
+thread_local! {
+    static PRESETS: RefCell<HashMap<String, Ruleset>> = RefCell::new(HashMap::new());
+}
+
+/// Returns the names of the presets that are always available.
+pub(crate) fn builtin_names() -> impl Iterator<Item = &'static str> {
+    BUILTIN.iter().map(|(name, _)| *name)
+}
+
+/// Makes the given presets available to [`Ruleset`] deserialization (on the current thread) for
+/// the duration of `f`, in addition to the builtin presets.
+pub(crate) fn with_presets<T>(presets: &HashMap<String, Ruleset>, f: impl FnOnce() -> T) -> T {
+    /// Puts back the previous presets, even if `f` panics.
+    struct Restore(Option<HashMap<String, Ruleset>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            if let Some(previous) = self.0.take() {
+                PRESETS.with(|p| p.replace(previous));
+            }
+        }
+    }
+
+    let _restore = Restore(Some(PRESETS.with(|p| p.replace(presets.clone()))));
+    f()
+}
+
+pub(super) fn get(name: &str) -> Result<Ruleset> {
+    if let Some(r) = PRESETS.with(|p| p.borrow().get(name).cloned()) {
+        return Ok(r);
+    }
+
+    match BUILTIN.iter().find(|(n, _)| *n == name) {
+        Some((_, s)) => Ok(toml::from_str(s)?),
+        None => Err(anyhow!("unknown ref matcher preset '{}'", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matching_rules::{Context, Match};
+    use git2::Oid;
+
+    #[test]
+    fn builtin_presets_parse() {
+        for name in builtin_names() {
+            get(name).unwrap();
+        }
+    }
+
+    #[test]
+    fn builtin_all_branches_and_tags() {
+        let rs: Ruleset = toml::from_str("rules = 'all-branches-and-tags'")
+            .map(|c: HashMap<String, Ruleset>| c["rules"].clone())
+            .unwrap();
+        assert!(rs.matches("refs/heads/feature/foo", Oid::zero()));
+        assert!(rs.matches("refs/tags/v1.0.0", Oid::zero()));
+        assert!(!rs.matches("refs/pull/1/head", Oid::zero()));
+    }
+
+    #[test]
+    fn builtin_releases_only() {
+        let rs = get("releases-only").unwrap();
+        let refs = [
+            ("refs/heads/main", Oid::zero()),
+            ("refs/tags/1.0.0", Oid::zero()),
+            ("refs/tags/v1.1.0", Oid::zero()),
+            ("refs/tags/v1.2.0-rc.1", Oid::zero()),
+            ("refs/tags/nightly", Oid::zero()),
+        ];
+        let selected: Vec<&str> = rs
+            .select(refs, &Context::default())
+            .into_iter()
+            .map(|r| r.0)
+            .collect();
+        assert_eq!(selected, vec!["refs/tags/1.0.0", "refs/tags/v1.1.0"]);
+    }
+
+    #[test]
+    fn user_presets() {
+        let presets: HashMap<String, Ruleset> = toml::from_str(
+            r#"
+            [main-only]
+            [[main-only.rules]]
+            type = 'exact'
+            expr = 'refs/heads/main'
+            "#,
+        )
+        .unwrap();
+
+        #[derive(serde::Deserialize)]
+        struct Config {
+            ref_matchers: Ruleset,
+        }
+
+        let c: Config =
+            with_presets(&presets, || toml::from_str("ref_matchers = 'main-only'")).unwrap();
+        assert!(c.ref_matchers.matches("refs/heads/main", Oid::zero()));
+        assert!(!c.ref_matchers.matches("refs/heads/develop", Oid::zero()));
+
+        // User presets are only available within `with_presets`
+        assert!(toml::from_str::<Config>("ref_matchers = 'main-only'").is_err());
+        assert!(toml::from_str::<Config>("ref_matchers = 'releases-only'").is_ok());
+    }
+
+    #[test]
+    fn presets_removed_after_panic() {
+        let presets = HashMap::from([("mine".to_string(), get("releases-only").unwrap())]);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            with_presets(&presets, || {
+                get("mine").unwrap();
+                panic!("while parsing");
+            })
+        }));
+        assert!(result.is_err());
+        assert!(get("mine").is_err());
+    }
+}
@@ -0,0 +1,189 @@
+use semver::{Version, VersionReq};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+
+const TAG_PREFIX: &str = "refs/tags/";
+
+/// Selects tags named as semantic versions (optionally with a prefix, e.g. `v1.2.3`).
+/// Tags whose names are not valid semantic versions are never selected.
+#[derive(Clone, Debug, Deserialize)]
+pub(super) struct SemverRule {
+    /// Prefix preceding the version in the tag name
+    #[serde(default)]
+    prefix: String,
+    /// Only select versions matching this requirement (e.g. `>=2.0, <4`)
+    range: Option<VersionReq>,
+    /// Only select this many of the most recent versions
+    latest: Option<usize>,
+    /// Only select the most recent patch release of each minor version
+    #[serde(default)]
+    latest_patch_per_minor: bool,
+    /// Never select pre-release versions
+    #[serde(default)]
+    exclude_prereleases: bool,
+}
+
+impl SemverRule {
+    fn version(&self, name: &str) -> Option<Version> {
+        let v = name.strip_prefix(TAG_PREFIX)?.strip_prefix(&self.prefix)?;
+        Version::parse(v).ok()
+    }
+
+    /// Returns the names of the refs selected from the given candidates.
+    pub(super) fn select<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> HashSet<&'a str> {
+        let mut versions: Vec<(Version, &str)> = names
+            .into_iter()
+            .filter_map(|n| self.version(n).map(|v| (v, n)))
+            .filter(|(v, _)| !self.exclude_prereleases || v.pre.is_empty())
+            .filter(|(v, _)| self.range.as_ref().is_none_or(|r| r.matches(v)))
+            .collect();
+
+        if self.latest_patch_per_minor {
+            let mut latest: BTreeMap<(u64, u64), (Version, &str)> = BTreeMap::new();
+            for (v, n) in versions {
+                let key = (v.major, v.minor);
+                if latest.get(&key).is_none_or(|(l, _)| v > *l) {
+                    latest.insert(key, (v, n));
+                }
+            }
+            versions = latest.into_values().collect();
+        }
+
+        versions.sort_by(|a, b| b.0.cmp(&a.0));
+        if let Some(n) = self.latest {
+            versions.truncate(n);
+        }
+
+        versions.into_iter().map(|(_, n)| n).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TAGS: &[&str] = &[
+        "refs/heads/main",
+        "refs/heads/v9.9.9",
+        "refs/tags/not-a-version",
+        "refs/tags/v1.0.0",
+        "refs/tags/v1.0.1",
+        "refs/tags/v1.1.0",
+        "refs/tags/v2.0.0-rc.1",
+        "refs/tags/v2.0.0",
+        "refs/tags/v2.0.1",
+        "refs/tags/v2.1.0",
+        "refs/tags/v2.1.1",
+        "refs/tags/v3.0.0",
+        "refs/tags/v4.0.0-beta.1",
+        "refs/tags/5.0.0",
+    ];
+
+    fn rule(toml: &str) -> SemverRule {
+        toml::from_str(toml).unwrap()
+    }
+
+    fn selected(rule: &SemverRule) -> Vec<&'static str> {
+        let mut s: Vec<&str> = rule.select(TAGS.iter().copied()).into_iter().collect();
+        s.sort();
+        s
+    }
+
+    #[test]
+    fn prefix() {
+        assert_eq!(selected(&rule("")), vec!["refs/tags/5.0.0"]);
+        assert_eq!(selected(&rule("prefix = 'v'")).len(), 10);
+    }
+
+    #[test]
+    fn range() {
+        let r = rule(
+            r#"
+            prefix = 'v'
+            range = '>=2.0, <4'
+            "#,
+        );
+        assert_eq!(
+            selected(&r),
+            vec![
+                "refs/tags/v2.0.0",
+                "refs/tags/v2.0.1",
+                "refs/tags/v2.1.0",
+                "refs/tags/v2.1.1",
+                "refs/tags/v3.0.0",
+            ]
+        );
+    }
+
+    #[test]
+    fn latest() {
+        let r = rule(
+            r#"
+            prefix = 'v'
+            latest = 3
+            "#,
+        );
+        assert_eq!(
+            selected(&r),
+            vec![
+                "refs/tags/v2.1.1",
+                "refs/tags/v3.0.0",
+                "refs/tags/v4.0.0-beta.1",
+            ]
+        );
+    }
+
+    #[test]
+    fn latest_excluding_prereleases() {
+        let r = rule(
+            r#"
+            prefix = 'v'
+            latest = 3
+            exclude_prereleases = true
+            "#,
+        );
+        assert_eq!(
+            selected(&r),
+            vec!["refs/tags/v2.1.0", "refs/tags/v2.1.1", "refs/tags/v3.0.0"]
+        );
+    }
+
+    #[test]
+    fn latest_patch_per_minor() {
+        let r = rule(
+            r#"
+            prefix = 'v'
+            latest_patch_per_minor = true
+            exclude_prereleases = true
+            "#,
+        );
+        assert_eq!(
+            selected(&r),
+            vec![
+                "refs/tags/v1.0.1",
+                "refs/tags/v1.1.0",
+                "refs/tags/v2.0.1",
+                "refs/tags/v2.1.1",
+                "refs/tags/v3.0.0",
+            ]
+        );
+    }
+
+    #[test]
+    fn combined() {
+        let r = rule(
+            r#"
+            prefix = 'v'
+            range = '>=1.0, <3'
+            latest_patch_per_minor = true
+            latest = 2
+            "#,
+        );
+        assert_eq!(selected(&r), vec!["refs/tags/v2.0.1", "refs/tags/v2.1.1"]);
+    }
+
+    #[test]
+    fn invalid_range() {
+        assert!(toml::from_str::<SemverRule>("range = 'nope'").is_err());
+    }
+}
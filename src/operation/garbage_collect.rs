@@ -1,10 +1,10 @@
 use crate::{
     config::RepositoryMapping,
     operation::{CommandError, CommandResult, CommandResultDetails},
+    scheduler::{self, Limits},
 };
 use anyhow::{Result, anyhow};
 use crossbeam_channel::Sender;
-use rayon::prelude::*;
 use std::{
     fmt,
     path::{Path, PathBuf},
@@ -36,13 +36,17 @@ fn gc(path: &Path) -> Result<()> {
     }
 }
 
-pub(super) fn run(
+pub(super) async fn run(
     mappings: &[RepositoryMapping],
+    limits: &Limits,
     s: Sender<CommandResult>,
 ) -> std::result::Result<(), usize> {
-    let failure_count = mappings
-        .par_iter()
-        .map(|m| {
+    // Garbage collection is entirely local, so only the overall limit applies
+    let failure_count = scheduler::run(
+        mappings.to_vec(),
+        limits,
+        |_| None,
+        move |m| {
             log::info!("Processing: {}", m.path.display());
 
             let result = gc(&m.path);
@@ -59,9 +63,12 @@ pub(super) fn run(
             .unwrap();
 
             result
-        })
-        .filter(|r| r.is_err())
-        .count();
+        },
+    )
+    .await
+    .into_iter()
+    .filter(|r| r.is_err())
+    .count();
 
     if failure_count == 0 {
         Ok(())
@@ -1,11 +1,12 @@
 mod garbage_collect;
 mod list;
 mod mirror;
+mod snapshot;
 mod stale;
 
-use crate::config::RepositoryMapping;
+use crate::{config::RepositoryMapping, scheduler::Limits};
 use anyhow::{Result, anyhow};
-use clap::Subcommand;
+use clap::{Args, Subcommand};
 use crossbeam_channel::{select, unbounded};
 use std::{fmt, thread};
 
@@ -25,6 +26,61 @@ pub(crate) enum Command {
     /// Identify stale/unmanaged local mirrors
     #[clap(name = "stale")]
     IdentifyStale(stale::Cli),
+
+    /// List or restore snapshots of the refs of local mirrors
+    #[clap(subcommand)]
+    Snapshot(snapshot::Cli),
+}
+
+/// How failures affect which repositories are processed and the exit status.
+#[derive(Clone, Debug, Default, Args)]
+pub(crate) struct FailurePolicy {
+    /// Process all repositories that were discovered, even if discovering others failed
+    #[clap(long)]
+    keep_going: bool,
+
+    /// Only exit with a failure if more than this percentage of repositories failed
+    #[clap(long, value_name = "PERCENT", default_value_t = 0.0)]
+    max_failed_percent: f64,
+
+    /// Do not count repositories or providers that could not be discovered as failures
+    #[clap(long, requires = "keep_going")]
+    discovery_failures_as_warnings: bool,
+}
+
+impl FailurePolicy {
+    fn check(&self, total: usize, discovery_failures: usize, job_failures: usize) -> Result<()> {
+        let discovery_failures = if self.discovery_failures_as_warnings {
+            0
+        } else {
+            discovery_failures
+        };
+        let failures = discovery_failures + job_failures;
+
+        if failures == 0 {
+            return Ok(());
+        }
+
+        let msg = if discovery_failures > 0 {
+            format!(
+                "{} jobs failed, {} discovery problems",
+                job_failures, discovery_failures
+            )
+        } else {
+            format!("{} jobs failed", job_failures)
+        };
+
+        if failures as f64 * 100.0 > self.max_failed_percent * total as f64 {
+            Err(anyhow!(msg))
+        } else {
+            log::warn!(
+                "{} (within the {}% threshold)",
+                msg,
+                self.max_failed_percent
+            );
+            Ok(())
+        }
+    }
 }
 
 pub(crate) type CommandResult =
@@ -46,7 +102,21 @@ impl fmt::Display for CommandError {
 }
 
 impl Command {
-    pub(crate) fn run(&self, mappings: Vec<Result<RepositoryMapping>>) -> Result<()> {
+    /// Whether the command needs the repositories of all providers to be discovered, which
+    /// usually means querying remote APIs.
+    pub(crate) fn needs_discovery(&self) -> bool {
+        match self {
+            Command::Snapshot(args) => args.needs_discovery(),
+            _ => true,
+        }
+    }
+
+    pub(crate) async fn run(
+        &self,
+        mappings: Vec<Result<RepositoryMapping>>,
+        limits: &Limits,
+        failure_policy: &FailurePolicy,
+    ) -> Result<()> {
         let (s, r) = unbounded::<CommandResult>();
 
         thread::spawn(move || {
@@ -69,26 +139,67 @@ impl Command {
             }
         });
 
-        if mappings.iter().any(|m| m.is_err()) {
-            for e in mappings.into_iter().flat_map(|m| m.err()) {
+        let total = mappings.len();
+        let (mappings, errors): (Vec<_>, Vec<_>) = mappings.into_iter().partition(|m| m.is_ok());
+        let discovery_failures = errors.len();
+
+        for e in errors.into_iter().flat_map(|m| m.err()) {
+            if failure_policy.discovery_failures_as_warnings {
+                log::warn!("{}", e);
+            } else {
                 log::error!("{}", e);
             }
-            Err(anyhow!("Configuration problems found"))
-        } else {
-            let mappings: Vec<RepositoryMapping> =
-                mappings.into_iter().flat_map(|m| m.ok()).collect();
-
-            let result = match &self {
-                Command::ListRepositories => list::run(&mappings),
-                Command::Mirror => mirror::run(&mappings, s),
-                Command::GarbageCollect => garbage_collect::run(&mappings, s),
-                Command::IdentifyStale(args) => stale::run(&mappings, args),
-            };
-
-            match result {
-                Ok(()) => Ok(()),
-                Err(c) => Err(anyhow!("{} jobs failed", c)),
-            }
         }
+
+        if discovery_failures > 0 && !failure_policy.keep_going {
+            return Err(anyhow!("Configuration problems found"));
+        }
+
+        let mappings: Vec<RepositoryMapping> = mappings.into_iter().flat_map(|m| m.ok()).collect();
+
+        let result = match &self {
+            Command::ListRepositories => list::run(&mappings),
+            Command::Mirror => mirror::run(&mappings, limits, s).await,
+            Command::GarbageCollect => garbage_collect::run(&mappings, limits, s).await,
+            Command::IdentifyStale(args) => stale::run(&mappings, args),
+            Command::Snapshot(args) => snapshot::run(&mappings, args),
+        };
+
+        failure_policy.check(total, discovery_failures, result.err().unwrap_or(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn any_failure() {
+        let p = FailurePolicy::default();
+        assert!(p.check(10, 0, 0).is_ok());
+        assert!(p.check(10, 0, 1).is_err());
+        assert!(p.check(10, 1, 0).is_err());
+    }
+
+    #[test]
+    fn percentage_threshold() {
+        let p = FailurePolicy {
+            max_failed_percent: 10.0,
+            ..Default::default()
+        };
+        assert!(p.check(20, 0, 2).is_ok());
+        assert!(p.check(20, 1, 2).is_err());
+        assert!(p.check(20, 0, 3).is_err());
+    }
+
+    #[test]
+    fn discovery_failures_as_warnings() {
+        let p = FailurePolicy {
+            keep_going: true,
+            discovery_failures_as_warnings: true,
+            ..Default::default()
+        };
+        assert!(p.check(10, 5, 0).is_ok());
+        assert!(p.check(10, 5, 1).is_err());
     }
 }
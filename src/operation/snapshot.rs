@@ -0,0 +1,100 @@
+use crate::{
+    config::RepositoryMapping,
+    snapshot::{self, Restored},
+};
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::Subcommand;
+use git2::Repository;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum Cli {
+    /// List the snapshots of each local mirror
+    #[clap(name = "ls")]
+    List {
+        /// Only list snapshots of these local mirrors
+        #[clap(value_name = "PATH")]
+        paths: Vec<PathBuf>,
+    },
+
+    /// Restore the refs of a local mirror to how they were at a given time
+    Restore {
+        /// Local mirror to restore
+        #[clap(value_name = "PATH")]
+        path: PathBuf,
+
+        /// Date (YYYY-MM-DD, meaning the end of that day in UTC) or RFC 3339 timestamp
+        #[clap(value_name = "TIME", value_parser = parse_time)]
+        time: DateTime<Utc>,
+    },
+}
+
+impl Cli {
+    /// Only listing the snapshots of every configured mirror needs them to be discovered, all
+    /// else works on the given local mirrors alone.
+    pub(super) fn needs_discovery(&self) -> bool {
+        matches!(self, Cli::List { paths } if paths.is_empty())
+    }
+}
+
+fn parse_time(s: &str) -> Result<DateTime<Utc>> {
+    match NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        Ok(d) => Ok(d.and_hms_opt(23, 59, 59).unwrap().and_utc()),
+        Err(_) => Ok(DateTime::parse_from_rfc3339(s)?.to_utc()),
+    }
+}
+
+fn list(path: &Path) -> Result<()> {
+    let repo = Repository::open(path)?;
+    println!("{}", path.display());
+    for s in snapshot::list(&repo)? {
+        println!("  {} {} ({} refs)", s.id, s.time.to_rfc3339(), s.refs.len());
+    }
+    Ok(())
+}
+
+fn restore(path: &Path, time: DateTime<Utc>) -> Result<()> {
+    let repo = Repository::open(path)
+        .map_err(|e| anyhow!("{} is not a local mirror: {}", path.display(), e))?;
+    let snapshot = snapshot::list(&repo)?
+        .into_iter()
+        .rev()
+        .find(|s| s.time <= time)
+        .ok_or_else(|| anyhow!("No snapshot of {} at or before {}", path.display(), time))?;
+
+    println!(
+        "Restoring {} to snapshot {} ({})",
+        path.display(),
+        snapshot.id,
+        snapshot.time.to_rfc3339()
+    );
+    for c in snapshot::restore(&repo, &snapshot)? {
+        match c {
+            Restored::Created(name, oid) => println!("[new] {} : {}", oid, name),
+            Restored::Updated(name, from, to) => println!("[chg] {} => {} : {}", from, to, name),
+            Restored::Deleted(name, oid) => println!("[del] {} : {}", oid, name),
+        }
+    }
+    Ok(())
+}
+
+pub(super) fn run(mappings: &[RepositoryMapping], args: &Cli) -> std::result::Result<(), usize> {
+    let results: Vec<Result<()>> = match args {
+        Cli::List { paths } if paths.is_empty() => mappings.iter().map(|m| list(&m.path)).collect(),
+        Cli::List { paths } => paths.iter().map(|p| list(p)).collect(),
+        Cli::Restore { path, time } => vec![restore(path, *time)],
+    };
+
+    let mut failure_count = 0;
+    for e in results.into_iter().filter_map(|r| r.err()) {
+        log::error!("{}", e);
+        failure_count += 1;
+    }
+
+    if failure_count == 0 {
+        Ok(())
+    } else {
+        Err(failure_count)
+    }
+}
@@ -0,0 +1,197 @@
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+
+/// Rewrites of remote ref names to the names used in the local mirror, in the style of a refspec.
+/// The first rewrite whose `from` pattern matches a ref is used, refs matching no rewrite keep
+/// their remote name.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(transparent)]
+pub(crate) struct RefRewrites(Vec<RefRewrite>);
+
+impl RefRewrites {
+    pub(crate) fn local_name(&self, remote_name: &str) -> String {
+        self.0
+            .iter()
+            .find_map(|r| r.apply(remote_name))
+            .unwrap_or_else(|| remote_name.to_string())
+    }
+
+    /// The remote names that are given `local_name` locally.
+    pub(crate) fn remote_names(&self, local_name: &str) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .0
+            .iter()
+            .filter_map(|r| r.reverse(local_name))
+            .chain([local_name.to_string()])
+            .filter(|n| self.local_name(n) == local_name)
+            .collect();
+        names.dedup();
+        names
+    }
+}
+
+/// A single rewrite from `from` to `to`.
+/// Either both or neither of the patterns contain a single `*`, which matches any sequence of
+/// characters (including `/`) and is substituted into `to`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "RefRewriteConfig")]
+struct RefRewrite {
+    from: String,
+    to: String,
+}
+
+#[derive(Deserialize)]
+struct RefRewriteConfig {
+    from: String,
+    to: String,
+}
+
+impl TryFrom<RefRewriteConfig> for RefRewrite {
+    type Error = anyhow::Error;
+
+    fn try_from(c: RefRewriteConfig) -> Result<Self> {
+        RefRewrite::new(&c.from, &c.to)
+    }
+}
+
+impl RefRewrite {
+    fn new(from: &str, to: &str) -> Result<Self> {
+        let from_wildcards = from.matches('*').count();
+        let to_wildcards = to.matches('*').count();
+
+        if from_wildcards > 1 || to_wildcards > 1 {
+            Err(anyhow!(
+                "ref rewrite patterns may contain at most one '*': {} -> {}",
+                from,
+                to
+            ))
+        } else if from_wildcards != to_wildcards {
+            Err(anyhow!(
+                "ref rewrite patterns must both or neither contain a '*': {} -> {}",
+                from,
+                to
+            ))
+        } else {
+            Ok(Self {
+                from: from.to_string(),
+                to: to.to_string(),
+            })
+        }
+    }
+
+    fn apply(&self, name: &str) -> Option<String> {
+        substitute(&self.from, &self.to, name)
+    }
+
+    /// The name this rewrite would give `name`, if any.
+    fn reverse(&self, name: &str) -> Option<String> {
+        substitute(&self.to, &self.from, name)
+    }
+}
+
+/// Rewrites `name` from the pattern `from` to the pattern `to`, if it matches `from`.
+fn substitute(from: &str, to: &str, name: &str) -> Option<String> {
+    match from.split_once('*') {
+        Some((prefix, suffix)) => {
+            let matched = name.strip_prefix(prefix)?.strip_suffix(suffix)?;
+            Some(to.replacen('*', matched, 1))
+        }
+        None => (name == from).then(|| to.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrite_namespace() {
+        let r = RefRewrite::new("refs/heads/*", "refs/upstream/heads/*").unwrap();
+        assert_eq!(
+            r.apply("refs/heads/main"),
+            Some("refs/upstream/heads/main".to_string())
+        );
+        assert_eq!(
+            r.apply("refs/heads/feature/foo"),
+            Some("refs/upstream/heads/feature/foo".to_string())
+        );
+        assert_eq!(r.apply("refs/tags/v1.0.0"), None);
+    }
+
+    #[test]
+    fn rewrite_flatten() {
+        let r = RefRewrite::new("refs/pull/*/head", "refs/pr/*").unwrap();
+        assert_eq!(r.apply("refs/pull/42/head"), Some("refs/pr/42".to_string()));
+        assert_eq!(r.apply("refs/pull/42/merge"), None);
+    }
+
+    #[test]
+    fn rewrite_exact() {
+        let r = RefRewrite::new("refs/heads/master", "refs/heads/main").unwrap();
+        assert_eq!(
+            r.apply("refs/heads/master"),
+            Some("refs/heads/main".to_string())
+        );
+        assert_eq!(r.apply("refs/heads/master2"), None);
+    }
+
+    #[test]
+    fn rewrite_invalid() {
+        assert!(RefRewrite::new("refs/*/*", "refs/*").is_err());
+        assert!(RefRewrite::new("refs/heads/*", "refs/heads/main").is_err());
+        assert!(RefRewrite::new("refs/heads/main", "refs/heads/*").is_err());
+    }
+
+    #[test]
+    fn rewrites_first_match() {
+        #[derive(Deserialize)]
+        struct Config {
+            ref_rewrites: RefRewrites,
+        }
+
+        let c: Config = toml::from_str(
+            r#"
+            ref_rewrites = [
+                { from = 'refs/pull/*/head', to = 'refs/pr/*' },
+                { from = 'refs/heads/main', to = 'refs/upstream/trunk' },
+                { from = 'refs/heads/*', to = 'refs/upstream/heads/*' },
+            ]
+            "#,
+        )
+        .unwrap();
+        let rw = c.ref_rewrites;
+
+        assert_eq!(rw.local_name("refs/pull/1/head"), "refs/pr/1");
+        assert_eq!(rw.local_name("refs/heads/main"), "refs/upstream/trunk");
+        assert_eq!(rw.local_name("refs/heads/dev"), "refs/upstream/heads/dev");
+        assert_eq!(rw.local_name("refs/tags/v1.0.0"), "refs/tags/v1.0.0");
+
+        assert_eq!(
+            rw.remote_names("refs/pr/1"),
+            vec!["refs/pull/1/head", "refs/pr/1"]
+        );
+        assert_eq!(
+            rw.remote_names("refs/upstream/trunk"),
+            vec!["refs/heads/main", "refs/upstream/trunk"]
+        );
+        assert_eq!(
+            rw.remote_names("refs/upstream/heads/dev"),
+            vec!["refs/heads/dev", "refs/upstream/heads/dev"]
+        );
+        assert_eq!(
+            rw.remote_names("refs/tags/v1.0.0"),
+            vec!["refs/tags/v1.0.0"]
+        );
+        // refs/heads/main is given a different name by an earlier rewrite
+        assert_eq!(
+            rw.remote_names("refs/upstream/heads/main"),
+            vec!["refs/upstream/heads/main"]
+        );
+        assert!(rw.remote_names("refs/heads/dev").is_empty());
+
+        assert!(
+            toml::from_str::<Config>("ref_rewrites = [{ from = 'refs/*/*', to = 'refs/*' }]")
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,407 @@
+use anyhow::{Error, Result};
+use serde::Deserialize;
+use std::{
+    fmt,
+    future::Future,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+/// Kinds of error that may go away by themselves, and so can be retried.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ErrorClass {
+    /// Failure to connect, or a connection dropping
+    Network,
+    /// HTTP 5xx responses
+    ServerError,
+    /// HTTP 429 responses and GitHub's rate limit responses
+    RateLimit,
+    /// An operation taking longer than its configured timeout
+    Timeout,
+}
+
+impl ErrorClass {
+    fn of_status(status: u16, message: &str) -> Option<Self> {
+        match status {
+            429 => Some(Self::RateLimit),
+            403 if message.to_lowercase().contains("rate limit") => Some(Self::RateLimit),
+            500..=599 => Some(Self::ServerError),
+            _ => None,
+        }
+    }
+
+    fn of_git(e: &git2::Error) -> Option<Self> {
+        if e.code() == git2::ErrorCode::Timeout {
+            return Some(Self::Timeout);
+        }
+        match e.class() {
+            git2::ErrorClass::Net | git2::ErrorClass::Ssl => Some(Self::Network),
+            // Socket errors are reported as OS errors
+            git2::ErrorClass::Os if e.message().starts_with("failed to connect") => {
+                Some(Self::Network)
+            }
+            git2::ErrorClass::Http => {
+                // libgit2 only reports HTTP statuses in the message
+                let status = e
+                    .message()
+                    .split_once("status code: ")
+                    .and_then(|(_, s)| s.get(..3))
+                    .and_then(|s| s.parse().ok());
+                match status {
+                    Some(s) => Self::of_status(s, e.message()),
+                    None => Some(Self::Network),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn of_reqwest(e: &reqwest::Error) -> Option<Self> {
+        if e.is_timeout() {
+            Some(Self::Timeout)
+        } else if let Some(status) = e.status() {
+            Self::of_status(status.as_u16(), &e.to_string())
+        } else if e.is_connect() || e.is_request() {
+            Some(Self::Network)
+        } else {
+            None
+        }
+    }
+
+    fn of_octocrab(e: &octocrab::Error) -> Option<Self> {
+        match e {
+            octocrab::Error::GitHub { source, .. } => {
+                Self::of_status(source.status_code.as_u16(), &source.message)
+            }
+            octocrab::Error::Hyper { .. } | octocrab::Error::Service { .. } => Some(Self::Network),
+            _ => None,
+        }
+    }
+
+    /// Classifies an error by the first error in its chain that is recognised.
+    pub(crate) fn of(e: &Error) -> Option<Self> {
+        e.chain().find_map(|e| {
+            if e.is::<TimedOut>() {
+                Some(Self::Timeout)
+            } else if let Some(e) = e.downcast_ref::<git2::Error>() {
+                Self::of_git(e)
+            } else if let Some(e) = e.downcast_ref::<reqwest::Error>() {
+                Self::of_reqwest(e)
+            } else if let Some(e) = e.downcast_ref::<octocrab::Error>() {
+                Self::of_octocrab(e)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// How failed operations are retried.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub(crate) struct RetryPolicy {
+    /// Total number of attempts, including the first
+    attempts: u32,
+    /// Delay before the first retry, doubled for each further retry
+    initial_backoff_ms: u64,
+    /// Upper limit of the delay between retries
+    max_backoff_ms: u64,
+    /// Classes of error that are retried, all others fail immediately
+    retry_on: Vec<ErrorClass>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            initial_backoff_ms: 1000,
+            max_backoff_ms: 60_000,
+            retry_on: vec![
+                ErrorClass::Network,
+                ErrorClass::ServerError,
+                ErrorClass::RateLimit,
+                ErrorClass::Timeout,
+            ],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retrying after the given (1-based) failed attempt, or `None` if the error
+    /// should not be retried.
+    fn backoff(&self, attempt: u32, e: &Error) -> Option<Duration> {
+        if attempt >= self.attempts
+            || !ErrorClass::of(e).is_some_and(|c| self.retry_on.contains(&c))
+        {
+            return None;
+        }
+
+        let max = self
+            .initial_backoff_ms
+            .saturating_mul(1 << (attempt - 1).min(32))
+            .min(self.max_backoff_ms);
+        // Jitter over the upper half of the delay so that jobs failing together do not all retry
+        // together
+        Some(Duration::from_millis(fastrand::u64(max / 2..=max)))
+    }
+
+    pub(crate) fn run_blocking<T>(
+        &self,
+        what: impl fmt::Display,
+        mut f: impl FnMut() -> Result<T>,
+    ) -> Result<T> {
+        let mut attempt = 1;
+        loop {
+            match f() {
+                Ok(v) => return Ok(v),
+                Err(e) => match self.backoff(attempt, &e) {
+                    Some(delay) => {
+                        log::warn!("{} failed (attempt {}), retrying: {}", what, attempt, e);
+                        thread::sleep(delay);
+                        attempt += 1;
+                    }
+                    None => return Err(e),
+                },
+            }
+        }
+    }
+
+    pub(crate) async fn run<T, F>(
+        &self,
+        what: impl fmt::Display,
+        mut f: impl FnMut() -> F,
+    ) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match f().await {
+                Ok(v) => return Ok(v),
+                Err(e) => match self.backoff(attempt, &e) {
+                    Some(delay) => {
+                        log::warn!("{} failed (attempt {}), retrying: {}", what, attempt, e);
+                        tokio::time::sleep(delay).await;
+                        attempt += 1;
+                    }
+                    None => return Err(e),
+                },
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct TimedOut(Duration);
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Timed out after {}s", self.0.as_secs())
+    }
+}
+
+impl std::error::Error for TimedOut {}
+
+/// Runs blocking `f` on its own thread, giving up on it after `timeout`.
+/// `f` is passed a flag that is set once it has been given up on, after which it should stop as
+/// soon as possible and must not make any further changes. It is still waited for, so that it is
+/// never running alongside a retry of the same operation.
+///
+/// Libgit2 only sees the flag in its progress callbacks, it is [`set_network_timeouts`] that stops
+/// it from waiting forever on an unresponsive server.
+pub(crate) fn with_timeout<T: Send + 'static>(
+    timeout: Option<Duration>,
+    f: impl FnOnce(&AtomicBool) -> Result<T> + Send + 'static,
+) -> Result<T> {
+    let cancelled = Arc::new(AtomicBool::new(false));
+
+    let Some(timeout) = timeout else {
+        return f(&cancelled);
+    };
+
+    let (s, r) = crossbeam_channel::bounded(1);
+    let worker = {
+        let cancelled = cancelled.clone();
+        thread::spawn(move || {
+            let _ = s.send(f(&cancelled));
+        })
+    };
+
+    match r.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(_) => {
+            cancelled.store(true, Ordering::SeqCst);
+            log::warn!(
+                "Timed out after {}s, waiting for the operation to stop",
+                timeout.as_secs()
+            );
+            let _ = worker.join();
+            Err(TimedOut(timeout).into())
+        }
+    }
+}
+
+/// Longest time libgit2 waits to connect to a server.
+const SERVER_CONNECT_TIMEOUT: Duration = Duration::from_secs(60);
+/// Longest time libgit2 waits for a server to send or accept any data.
+const SERVER_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Stops libgit2 from waiting forever on unresponsive servers, including while connecting and
+/// during the handshake, where a timed out operation cannot otherwise be interrupted.
+///
+/// # Safety
+/// Sets process wide libgit2 options, so must be called before any other threads are started.
+pub(crate) unsafe fn set_network_timeouts() -> Result<()> {
+    unsafe {
+        git2::opts::set_server_connect_timeout_in_milliseconds(
+            SERVER_CONNECT_TIMEOUT.as_millis() as i32
+        )?;
+        git2::opts::set_server_timeout_in_milliseconds(SERVER_TIMEOUT.as_millis() as i32)?;
+    }
+    Ok(())
+}
+
+/// Awaits `f`, giving up on it after `timeout`.
+pub(crate) async fn with_async_timeout<T>(
+    timeout: Option<Duration>,
+    f: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, f)
+            .await
+            .map_err(|_| TimedOut(timeout))?,
+        None => f.await,
+    }
+}
+
+/// Timeouts for the network operations of a provider, in seconds.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct Timeouts {
+    /// Mirroring a single repository
+    mirror_secs: Option<u64>,
+    /// Discovering the repositories of the provider
+    discovery_secs: Option<u64>,
+}
+
+impl Timeouts {
+    pub(crate) fn mirror(&self) -> Option<Duration> {
+        self.mirror_secs.map(Duration::from_secs)
+    }
+
+    pub(crate) fn discovery(&self) -> Option<Duration> {
+        self.discovery_secs.map(Duration::from_secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use std::sync::atomic::AtomicU32;
+
+    fn fast() -> RetryPolicy {
+        RetryPolicy {
+            initial_backoff_ms: 1,
+            max_backoff_ms: 2,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn classify() {
+        let net = git2::Error::new(git2::ErrorCode::GenericError, git2::ErrorClass::Net, "x");
+        assert_eq!(ErrorClass::of(&net.into()), Some(ErrorClass::Network));
+
+        let http = git2::Error::new(
+            git2::ErrorCode::GenericError,
+            git2::ErrorClass::Http,
+            "unexpected http status code: 502",
+        );
+        assert_eq!(
+            ErrorClass::of(&Error::from(http).context("fetching")),
+            Some(ErrorClass::ServerError)
+        );
+
+        let auth = git2::Error::new(
+            git2::ErrorCode::Auth,
+            git2::ErrorClass::Http,
+            "unexpected http status code: 401",
+        );
+        assert_eq!(ErrorClass::of(&auth.into()), None);
+
+        assert_eq!(
+            ErrorClass::of(&TimedOut(Duration::from_secs(1)).into()),
+            Some(ErrorClass::Timeout)
+        );
+        assert_eq!(ErrorClass::of(&anyhow!("Matched zero remote refs")), None);
+    }
+
+    #[test]
+    fn backoff() {
+        let p = RetryPolicy::default();
+        let net: Error =
+            git2::Error::new(git2::ErrorCode::GenericError, git2::ErrorClass::Net, "x").into();
+
+        let d = p.backoff(1, &net).unwrap();
+        assert!(d >= Duration::from_millis(500) && d <= Duration::from_millis(1000));
+        let d = p.backoff(2, &net).unwrap();
+        assert!(d >= Duration::from_millis(1000) && d <= Duration::from_millis(2000));
+        assert!(p.backoff(3, &net).is_none());
+        assert!(p.backoff(1, &anyhow!("not transient")).is_none());
+
+        let p = RetryPolicy {
+            attempts: 100,
+            ..Default::default()
+        };
+        assert!(p.backoff(50, &net).unwrap() <= Duration::from_secs(60));
+    }
+
+    #[test]
+    fn retries_until_success() {
+        let calls = AtomicU32::new(0);
+        let result = fast().run_blocking("test", || {
+            if calls.fetch_add(1, Ordering::SeqCst) < 2 {
+                Err(TimedOut(Duration::ZERO).into())
+            } else {
+                Ok(())
+            }
+        });
+        assert!(result.is_ok());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn timeout() {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let result = {
+            let stopped = stopped.clone();
+            with_timeout(Some(Duration::from_millis(10)), move |cancelled| {
+                while !cancelled.load(Ordering::SeqCst) {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                thread::sleep(Duration::from_millis(20));
+                stopped.store(true, Ordering::SeqCst);
+                Ok(())
+            })
+        };
+        assert!(result.unwrap_err().is::<TimedOut>());
+        // The abandoned attempt has finished before the timeout is reported
+        assert!(stopped.load(Ordering::SeqCst));
+
+        assert_eq!(with_timeout(None, |_| Ok(1)).unwrap(), 1);
+    }
+
+    #[test]
+    fn deserialize() {
+        let p: RetryPolicy = toml::from_str("attempts = 5\nretry_on = ['rate_limit']").unwrap();
+        assert_eq!(p.attempts, 5);
+        assert_eq!(p.initial_backoff_ms, 1000);
+        assert_eq!(p.retry_on, vec![ErrorClass::RateLimit]);
+    }
+}
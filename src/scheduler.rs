@@ -0,0 +1,209 @@
+use anyhow::{Result, anyhow};
+use clap::Args;
+use std::{
+    any::Any,
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::Arc,
+};
+use tokio::{
+    sync::Semaphore,
+    task::{JoinError, JoinSet},
+};
+
+#[derive(Clone, Debug, Args)]
+pub(crate) struct Limits {
+    /// Maximum number of repositories to process at once
+    #[clap(short, long, default_value_t = 16)]
+    pub jobs: usize,
+
+    /// Maximum number of repositories on the same host to process at once
+    #[clap(long, default_value_t = 4)]
+    pub jobs_per_host: usize,
+}
+
+/// Orders items so that consecutive items are on different hosts wherever possible, keeping the
+/// relative order of items on the same host.
+fn interleave_by_host<T>(items: Vec<T>, host: impl Fn(&T) -> Option<String>) -> Vec<T> {
+    let mut hosts: Vec<VecDeque<T>> = Vec::new();
+    let mut index: HashMap<Option<String>, usize> = HashMap::new();
+
+    for item in items {
+        let i = *index.entry(host(&item)).or_insert_with(|| {
+            hosts.push(VecDeque::new());
+            hosts.len() - 1
+        });
+        hosts[i].push_back(item);
+    }
+
+    let mut ordered = Vec::new();
+    while hosts.iter().any(|h| !h.is_empty()) {
+        for h in &mut hosts {
+            ordered.extend(h.pop_front());
+        }
+    }
+    ordered
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown cause")
+}
+
+/// Turns a job that panicked (or was cancelled) into a failure of that item alone.
+fn job_error(item: &str, e: JoinError) -> anyhow::Error {
+    let e = match e.try_into_panic() {
+        Ok(payload) => anyhow!("{} panicked: {}", item, panic_message(payload.as_ref())),
+        Err(e) => anyhow!("{} failed: {}", item, e),
+    };
+    log::error!("{}", e);
+    e
+}
+
+/// Runs the blocking function `f` for each item, within the given limits.
+/// Items for which `host` returns `None` are only subject to the overall limit.
+/// An item whose job panics fails with an error, without affecting the others.
+pub(crate) async fn run<T, R>(
+    items: Vec<T>,
+    limits: &Limits,
+    host: impl Fn(&T) -> Option<String>,
+    f: impl Fn(T) -> Result<R> + Send + Sync + 'static,
+) -> Vec<Result<R>>
+where
+    T: fmt::Display + Send + 'static,
+    R: Send + 'static,
+{
+    let f = Arc::new(f);
+    let global = Arc::new(Semaphore::new(limits.jobs.max(1)));
+    let mut hosts: HashMap<String, Arc<Semaphore>> = HashMap::new();
+    let mut tasks = JoinSet::new();
+
+    for item in interleave_by_host(items, &host) {
+        let host = host(&item).map(|h| {
+            hosts
+                .entry(h)
+                .or_insert_with(|| Arc::new(Semaphore::new(limits.jobs_per_host.max(1))))
+                .clone()
+        });
+        let global = global.clone();
+        let f = f.clone();
+
+        tasks.spawn(async move {
+            // The host permit is taken first so that items waiting on a busy host do not hold up
+            // items on other hosts
+            let _host = match host {
+                Some(h) => Some(h.acquire_owned().await?),
+                None => None,
+            };
+            let _global = global.acquire_owned().await?;
+            let name = item.to_string();
+            tokio::task::spawn_blocking(move || f(item))
+                .await
+                .map_err(|e| job_error(&name, e))?
+        });
+    }
+
+    tasks.join_all().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::{
+            Mutex,
+            atomic::{AtomicUsize, Ordering},
+        },
+        thread,
+        time::Duration,
+    };
+
+    fn host(item: &&'static str) -> Option<String> {
+        item.split_once('/').map(|(h, _)| h.to_string())
+    }
+
+    #[test]
+    fn interleave() {
+        let items = vec!["a/1", "a/2", "a/3", "b/1", "local", "b/2", "c/1"];
+        assert_eq!(
+            interleave_by_host(items, host),
+            vec!["a/1", "b/1", "local", "c/1", "a/2", "b/2", "a/3"]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn limits() {
+        let items: Vec<&'static str> = (0..12)
+            .map(|i| if i % 3 == 0 { "b/x" } else { "a/x" })
+            .collect();
+
+        let running: Arc<Mutex<HashMap<Option<String>, usize>>> = Default::default();
+        let max_per_host: Arc<Mutex<HashMap<Option<String>, usize>>> = Default::default();
+        let total = Arc::new(AtomicUsize::new(0));
+        let max_total = Arc::new(AtomicUsize::new(0));
+
+        let limits = Limits {
+            jobs: 3,
+            jobs_per_host: 2,
+        };
+
+        let results = {
+            let (running, max_per_host, total, max_total) = (
+                running.clone(),
+                max_per_host.clone(),
+                total.clone(),
+                max_total.clone(),
+            );
+            run(items, &limits, host, move |item| {
+                let h = host(&item);
+                {
+                    let mut running = running.lock().unwrap();
+                    let n = running.entry(h.clone()).or_default();
+                    *n += 1;
+                    let mut max = max_per_host.lock().unwrap();
+                    let m = max.entry(h.clone()).or_default();
+                    *m = (*m).max(*n);
+                }
+                let t = total.fetch_add(1, Ordering::SeqCst) + 1;
+                max_total.fetch_max(t, Ordering::SeqCst);
+
+                thread::sleep(Duration::from_millis(20));
+
+                total.fetch_sub(1, Ordering::SeqCst);
+                *running.lock().unwrap().get_mut(&h).unwrap() -= 1;
+                Ok(item)
+            })
+            .await
+        };
+
+        assert_eq!(results.len(), 12);
+        assert!(max_total.load(Ordering::SeqCst) <= 3);
+        assert!(max_per_host.lock().unwrap().values().all(|m| *m <= 2));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn panic_fails_one_item() {
+        let limits = Limits {
+            jobs: 2,
+            jobs_per_host: 2,
+        };
+        let results = run(vec!["a/ok", "a/panic", "b/ok"], &limits, host, |item| {
+            if item.ends_with("panic") {
+                panic!("boom");
+            }
+            Ok(item)
+        })
+        .await;
+
+        assert_eq!(results.len(), 3);
+        let errors: Vec<String> = results
+            .iter()
+            .filter_map(|r| r.as_ref().err())
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(errors, vec!["a/panic panicked: boom"]);
+    }
+}
@@ -0,0 +1,108 @@
+use anyhow::{Result, anyhow};
+use rhai::{AST, Engine, Scope};
+use serde::{Deserialize, Deserializer, de::Visitor};
+use std::{fmt, sync::Arc};
+
+/// Upper bound on the work a single script evaluation may do, protects against runaway loops.
+const MAX_OPERATIONS: u64 = 100_000;
+
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_expr_depths(64, 32)
+        .set_max_string_size(64 * 1024)
+        .set_max_array_size(10_000)
+        .disable_symbol("eval")
+        .on_print(|s| log::info!("script: {}", s))
+        .on_debug(|s, _, pos| log::debug!("script ({}): {}", pos, s));
+    engine
+}
+
+/// A sandboxed Rhai script that evaluates to a boolean.
+#[derive(Clone, Debug)]
+pub(crate) struct Script {
+    /// The engine the script was compiled with, which is shared by clones of the script
+    engine: Arc<Engine>,
+    ast: AST,
+}
+
+impl Script {
+    pub(crate) fn new(source: &str) -> Result<Self> {
+        let engine = engine();
+        let ast = engine
+            .compile(source)
+            .map_err(|e| anyhow!("failed to parse script: {}", e))?;
+        Ok(Self {
+            engine: Arc::new(engine),
+            ast,
+        })
+    }
+
+    pub(crate) fn eval(&self, scope: &mut Scope) -> Result<bool> {
+        self.engine
+            .eval_ast_with_scope::<bool>(scope, &self.ast)
+            .map_err(|e| anyhow!("script evaluation failed: {}", e))
+    }
+}
+
+impl<'de> Deserialize<'de> for Script {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(ScriptVisitor)
+    }
+}
+
+struct ScriptVisitor;
+
+impl Visitor<'_> for ScriptVisitor {
+    type Value = Script;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a valid Rhai script")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Script::new(v).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eval_bool() {
+        let s = Script::new("x > 2").unwrap();
+
+        let mut scope = Scope::new();
+        scope.push("x", 3_i64);
+        assert!(s.eval(&mut scope).unwrap());
+
+        let mut scope = Scope::new();
+        scope.push("x", 1_i64);
+        assert!(!s.eval(&mut scope).unwrap());
+    }
+
+    #[test]
+    fn eval_non_bool() {
+        let s = Script::new("\"nope\"").unwrap();
+        assert!(s.eval(&mut Scope::new()).is_err());
+    }
+
+    #[test]
+    fn parse_error() {
+        assert!(Script::new("x >").is_err());
+    }
+
+    #[test]
+    fn runaway_loop() {
+        let s = Script::new("loop {}").unwrap();
+        assert!(s.eval(&mut Scope::new()).is_err());
+    }
+}
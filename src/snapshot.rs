@@ -0,0 +1,384 @@
+use crate::deleted_refs::COLLAGE_REF_PREFIX;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, TimeDelta, Utc};
+use git2::{Commit, ObjectType, Oid, Repository, Signature, Sort, Time};
+use serde::Deserialize;
+use std::{collections::HashSet, str};
+
+/// Ref holding the history of snapshots, each a commit whose tree contains a single `refs` file
+/// listing every ref (outside of [`COLLAGE_REF_PREFIX`]) and its target at the time.
+const SNAPSHOT_REF: &str = "refs/collage/snapshots";
+const SNAPSHOT_FILE: &str = "refs";
+
+/// Namespace holding a ref for every object a snapshot has recorded, named after its id, as the
+/// snapshots themselves do not keep those objects from being pruned.
+const OBJECTS_REF_PREFIX: &str = "refs/collage/snapshot-objects/";
+
+/// How long snapshots are kept.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub(crate) struct SnapshotPolicy {
+    /// Snapshots older than this are removed, along with the objects only they kept. The latest
+    /// snapshot, which records the current refs, is always kept.
+    /// If not given, snapshots (and every object they recorded) are kept forever.
+    pub expire_after_days: Option<u32>,
+}
+
+impl SnapshotPolicy {
+    /// Removes the snapshots that have expired by `now`, returning how many were removed.
+    pub(crate) fn expire(&self, repo: &Repository, now: DateTime<Utc>) -> Result<usize> {
+        let Some(days) = self.expire_after_days else {
+            return Ok(0);
+        };
+
+        let snapshots = list(repo)?;
+        let cutoff = now - TimeDelta::days(i64::from(days));
+        let expired = snapshots
+            .iter()
+            .position(|s| s.time >= cutoff)
+            .unwrap_or(snapshots.len())
+            .min(snapshots.len().saturating_sub(1));
+        if expired == 0 {
+            return Ok(0);
+        }
+
+        // Snapshots form a chain, so the kept ones are recreated without the expired ones
+        let kept = &snapshots[expired..];
+        let mut parent: Option<Commit> = None;
+        for s in kept {
+            let c = repo.find_commit(s.id)?;
+            let parents: Vec<_> = parent.iter().collect();
+            let id = repo.commit(
+                None,
+                &c.author(),
+                &c.committer(),
+                c.message().unwrap_or_default(),
+                &c.tree()?,
+                &parents,
+            )?;
+            parent = Some(repo.find_commit(id)?);
+        }
+        repo.reference(
+            SNAPSHOT_REF,
+            parent.unwrap().id(),
+            true,
+            "git-collage expire snapshots",
+        )?;
+
+        let recorded: HashSet<Oid> = kept
+            .iter()
+            .flat_map(|s| s.refs.iter().map(|(_, oid)| *oid))
+            .collect();
+        for r in repo.references_glob(&format!("{}*", OBJECTS_REF_PREFIX))? {
+            let mut r = r?;
+            if r.target().is_some_and(|oid| !recorded.contains(&oid)) {
+                r.delete()?;
+            }
+        }
+
+        Ok(expired)
+    }
+}
+
+pub(crate) struct Snapshot {
+    pub id: Oid,
+    pub time: DateTime<Utc>,
+    pub refs: Vec<(String, Oid)>,
+}
+
+fn current_refs(repo: &Repository) -> Result<Vec<(String, Oid)>> {
+    let mut refs = Vec::new();
+    for r in repo.references()? {
+        let r = r?;
+        if let (Some(name), Some(oid)) = (r.name(), r.target())
+            && !name.starts_with(COLLAGE_REF_PREFIX)
+        {
+            refs.push((name.to_string(), oid));
+        }
+    }
+    refs.sort();
+    Ok(refs)
+}
+
+fn serialize(refs: &[(String, Oid)]) -> String {
+    refs.iter()
+        .map(|(name, oid)| format!("{} {}\n", oid, name))
+        .collect()
+}
+
+fn deserialize(s: &str) -> Result<Vec<(String, Oid)>> {
+    s.lines()
+        .map(|l| {
+            let (oid, name) = l
+                .split_once(' ')
+                .ok_or_else(|| anyhow!("malformed snapshot line: {}", l))?;
+            Ok((name.to_string(), Oid::from_str(oid)?))
+        })
+        .collect()
+}
+
+/// Keeps the targets of `refs` reachable for as long as the snapshots recording them exist.
+fn keep_objects(repo: &Repository, refs: &[(String, Oid)]) -> Result<()> {
+    for (_, oid) in refs {
+        let name = format!("{}{}", OBJECTS_REF_PREFIX, oid);
+        if repo.find_reference(&name).is_err() {
+            repo.reference(&name, *oid, false, "git-collage snapshot")?;
+        }
+    }
+    Ok(())
+}
+
+/// Records the current refs as a new snapshot, unless they are unchanged since the last one.
+pub(crate) fn record(repo: &Repository, time: DateTime<Utc>) -> Result<Option<Oid>> {
+    let refs = current_refs(repo)?;
+    // Also done for unchanged refs, so that snapshots recorded before objects were kept are
+    // covered from now on
+    keep_objects(repo, &refs)?;
+    let contents = serialize(&refs);
+
+    let parent = match repo.find_reference(SNAPSHOT_REF) {
+        Ok(r) => Some(r.peel_to_commit()?),
+        Err(_) => None,
+    };
+
+    if let Some(p) = &parent
+        && let Some(entry) = p.tree()?.get_name(SNAPSHOT_FILE)
+        && entry.to_object(repo)?.peel_to_blob()?.content() == contents.as_bytes()
+    {
+        return Ok(None);
+    }
+
+    let blob = repo.blob(contents.as_bytes())?;
+    let mut tree = repo.treebuilder(None)?;
+    tree.insert(SNAPSHOT_FILE, blob, 0o100644)?;
+    let tree = repo.find_tree(tree.write()?)?;
+
+    let sig = Signature::new(
+        "git-collage",
+        "git-collage@localhost",
+        &Time::new(time.timestamp(), 0),
+    )?;
+    let parents: Vec<_> = parent.iter().collect();
+
+    Ok(Some(repo.commit(
+        Some(SNAPSHOT_REF),
+        &sig,
+        &sig,
+        "git-collage snapshot",
+        &tree,
+        &parents,
+    )?))
+}
+
+/// Lists all snapshots, oldest first.
+pub(crate) fn list(repo: &Repository) -> Result<Vec<Snapshot>> {
+    let head = match repo.find_reference(SNAPSHOT_REF) {
+        Ok(r) => r.peel_to_commit()?,
+        Err(_) => return Ok(Vec::new()),
+    };
+
+    let mut walk = repo.revwalk()?;
+    walk.push(head.id())?;
+    walk.simplify_first_parent()?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+
+    walk.map(|id| {
+        let commit = repo.find_commit(id?)?;
+        let blob = commit
+            .tree()?
+            .get_name(SNAPSHOT_FILE)
+            .ok_or_else(|| anyhow!("snapshot {} has no {} file", commit.id(), SNAPSHOT_FILE))?
+            .to_object(repo)?
+            .peel_to_blob()?;
+
+        Ok(Snapshot {
+            id: commit.id(),
+            time: DateTime::from_timestamp(commit.committer().when().seconds(), 0).unwrap(),
+            refs: deserialize(str::from_utf8(blob.content())?)?,
+        })
+    })
+    .collect()
+}
+
+/// Change made to a ref by [`restore`].
+pub(crate) enum Restored {
+    Created(String, Oid),
+    Updated(String, Oid, Oid),
+    Deleted(String, Oid),
+}
+
+/// Sets all refs (outside of [`COLLAGE_REF_PREFIX`]) to how they were in `snapshot`.
+/// The current refs are recorded as a snapshot first, so a restore can itself be undone.
+pub(crate) fn restore(repo: &Repository, snapshot: &Snapshot) -> Result<Vec<Restored>> {
+    // Check all objects are still present before changing anything
+    for (name, oid) in &snapshot.refs {
+        repo.find_object(*oid, Some(ObjectType::Any))
+            .map_err(|_| anyhow!("object {} for {} no longer exists", oid, name))?;
+    }
+
+    record(repo, Utc::now())?;
+
+    let current = current_refs(repo)?;
+    let mut changes = Vec::new();
+
+    for (name, oid) in &current {
+        if !snapshot.refs.iter().any(|(n, _)| n == name) {
+            repo.find_reference(name)?.delete()?;
+            changes.push(Restored::Deleted(name.clone(), *oid));
+        }
+    }
+
+    for (name, oid) in &snapshot.refs {
+        match current.iter().find(|(n, _)| n == name) {
+            Some((_, o)) if o == oid => {}
+            previous => {
+                repo.reference(name, *oid, true, "git-collage restore snapshot")?;
+                changes.push(match previous {
+                    Some((_, o)) => Restored::Updated(name.clone(), *o, *oid),
+                    None => Restored::Created(name.clone(), *oid),
+                });
+            }
+        }
+    }
+
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::testing::{commit, repo_with_commit};
+    use chrono::TimeDelta;
+
+    #[test]
+    fn record_and_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let (repo, base) = repo_with_commit(dir.path(), 0);
+        let t = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+
+        assert!(record(&repo, t).unwrap().is_some());
+        // Unchanged refs do not produce a new snapshot
+        assert!(record(&repo, t + TimeDelta::hours(1)).unwrap().is_none());
+
+        let next = commit(&repo, "refs/heads/main", 1, &[base]);
+        repo.reference("refs/tags/v1", base, false, "").unwrap();
+        assert!(record(&repo, t + TimeDelta::hours(2)).unwrap().is_some());
+
+        let snapshots = list(&repo).unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].time, t);
+        assert_eq!(
+            snapshots[0].refs,
+            vec![("refs/heads/main".to_string(), base)]
+        );
+        assert_eq!(snapshots[1].time, t + TimeDelta::hours(2));
+        assert_eq!(
+            snapshots[1].refs,
+            vec![
+                ("refs/heads/main".to_string(), next),
+                ("refs/tags/v1".to_string(), base)
+            ]
+        );
+    }
+
+    #[test]
+    fn restore_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let (repo, base) = repo_with_commit(dir.path(), 0);
+        repo.reference("refs/heads/old", base, false, "").unwrap();
+        record(&repo, Utc::now()).unwrap();
+
+        let next = commit(&repo, "refs/heads/main", 1, &[base]);
+        repo.find_reference("refs/heads/old")
+            .unwrap()
+            .delete()
+            .unwrap();
+        repo.reference("refs/heads/new", next, false, "").unwrap();
+
+        let snapshots = list(&repo).unwrap();
+        let changes = restore(&repo, &snapshots[0]).unwrap();
+        assert_eq!(changes.len(), 3);
+
+        assert_eq!(current_refs(&repo).unwrap(), snapshots[0].refs);
+        // The state before restoring was recorded
+        let snapshots = list(&repo).unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert!(
+            snapshots[1]
+                .refs
+                .contains(&("refs/heads/new".to_string(), next))
+        );
+    }
+
+    #[test]
+    fn restore_after_gc() {
+        let dir = tempfile::tempdir().unwrap();
+        let (repo, base) = repo_with_commit(dir.path(), 0);
+        let old = commit(&repo, "refs/heads/old", 1, &[base]);
+        record(&repo, Utc::now()).unwrap();
+
+        repo.find_reference("refs/heads/old")
+            .unwrap()
+            .delete()
+            .unwrap();
+        record(&repo, Utc::now()).unwrap();
+
+        for args in [
+            &["reflog", "expire", "--expire=now", "--all"][..],
+            &["gc", "--prune=now", "--quiet"],
+        ] {
+            let status = std::process::Command::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args(args)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        }
+
+        let snapshots = list(&repo).unwrap();
+        restore(&repo, &snapshots[0]).unwrap();
+        assert_eq!(repo.refname_to_id("refs/heads/old").unwrap(), old);
+    }
+
+    #[test]
+    fn expire() {
+        let dir = tempfile::tempdir().unwrap();
+        let (repo, base) = repo_with_commit(dir.path(), 0);
+        let old = commit(&repo, "refs/heads/old", 1, &[base]);
+        let t = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        record(&repo, t).unwrap();
+
+        repo.find_reference("refs/heads/old")
+            .unwrap()
+            .delete()
+            .unwrap();
+        record(&repo, t + TimeDelta::days(10)).unwrap();
+
+        let policy = SnapshotPolicy {
+            expire_after_days: Some(7),
+        };
+        assert_eq!(policy.expire(&repo, t + TimeDelta::days(7)).unwrap(), 0);
+        assert_eq!(policy.expire(&repo, t + TimeDelta::days(8)).unwrap(), 1);
+
+        let snapshots = list(&repo).unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].time, t + TimeDelta::days(10));
+        assert_eq!(
+            snapshots[0].refs,
+            vec![("refs/heads/main".to_string(), base)]
+        );
+        // Only the expired snapshot recorded the old branch
+        assert!(
+            repo.find_reference(&format!("{}{}", OBJECTS_REF_PREFIX, old))
+                .is_err()
+        );
+        assert!(
+            repo.find_reference(&format!("{}{}", OBJECTS_REF_PREFIX, base))
+                .is_ok()
+        );
+
+        // The latest snapshot is kept however old it is
+        assert_eq!(policy.expire(&repo, t + TimeDelta::days(100)).unwrap(), 0);
+        assert_eq!(list(&repo).unwrap().len(), 1);
+    }
+}
@@ -1,13 +1,18 @@
-use crate::source::{SourceRepositoryMapping, SourceRepositoryMappingProducer};
+use crate::{
+    credentials::CredentialSource,
+    source::{RepositoryMetadata, SourceRepositoryMapping, SourceRepositoryMappingProducer},
+};
 use anyhow::{Result, anyhow};
 use octocrab::Octocrab;
 use serde::{Deserialize, Serialize};
-use std::env;
 use std::fmt::Display;
+use url::Url;
 
 #[derive(Debug, Deserialize)]
 pub(crate) struct GithubAuthenticatedUser {
-    token_env_var: String,
+    /// API token, also used to fetch the repositories
+    #[serde(alias = "token_env_var")]
+    token: CredentialSource,
     #[serde(flatten)]
     visibility: Visibilities,
     #[serde(flatten)]
@@ -16,12 +21,11 @@ pub(crate) struct GithubAuthenticatedUser {
 
 impl SourceRepositoryMappingProducer for GithubAuthenticatedUser {
     async fn repository_mappings(&self) -> Result<Vec<SourceRepositoryMapping>> {
-        let token = env::var(&self.token_env_var).map_err(|_| {
-            anyhow!(
-                "Token environment variable '{}' not set",
-                self.token_env_var
-            )
-        })?;
+        let token = self
+            .token
+            .get(&Url::parse("https://github.com")?)
+            .map_err(|e| anyhow!("Failed to get GitHub token: {}", e))?
+            .secret;
 
         let octocrab = Octocrab::builder().personal_token(token.clone()).build()?;
 
@@ -58,7 +62,23 @@ impl SourceRepositoryMappingProducer for GithubAuthenticatedUser {
                     .parse()
                     .unwrap(),
                     ref_match: None,
-                    git_url,
+                    ref_rewrites: None,
+                    git_url: git_url.into(),
+                    metadata: RepositoryMetadata {
+                        fork: r.fork,
+                        archived: r.archived,
+                        visibility: r.visibility.clone(),
+                        size_kb: r.size.map(u64::from),
+                        default_branch: r.default_branch.clone(),
+                        pushed_at: r.pushed_at,
+                        language: r
+                            .language
+                            .as_ref()
+                            .and_then(|l| l.as_str())
+                            .map(|l| l.to_string()),
+                        topics: r.topics.clone().unwrap_or_default(),
+                        description: r.description.clone(),
+                    },
                 }
             })
             .collect())
@@ -3,12 +3,14 @@ mod static_list;
 
 use crate::{
     matching_rules::Ruleset,
+    ref_rewrite::RefRewrites,
+    remote_address::RemoteAddress,
     source::{github_authed_user::GithubAuthenticatedUser, static_list::StaticList},
 };
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
 use std::path::PathBuf;
-use url::Url;
 
 pub(crate) trait SourceRepositoryMappingProducer {
     async fn repository_mappings(&self) -> Result<Vec<SourceRepositoryMapping>>;
@@ -18,7 +20,24 @@ pub(crate) trait SourceRepositoryMappingProducer {
 pub(crate) struct SourceRepositoryMapping {
     pub path: PathBuf,
     pub ref_match: Option<Ruleset>,
-    pub git_url: Url,
+    pub ref_rewrites: Option<RefRewrites>,
+    pub git_url: RemoteAddress,
+    pub metadata: RepositoryMetadata,
+}
+
+/// Information about a repository that is known to the provider that discovered it.
+/// Every field is optional as not all providers (e.g. static lists) know anything beyond the URL.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RepositoryMetadata {
+    pub fork: Option<bool>,
+    pub archived: Option<bool>,
+    pub visibility: Option<String>,
+    pub size_kb: Option<u64>,
+    pub default_branch: Option<String>,
+    pub pushed_at: Option<DateTime<Utc>>,
+    pub language: Option<String>,
+    pub topics: Vec<String>,
+    pub description: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -1,11 +1,12 @@
 use crate::{
     matching_rules::Ruleset,
-    source::{SourceRepositoryMapping, SourceRepositoryMappingProducer},
+    ref_rewrite::RefRewrites,
+    remote_address::RemoteAddress,
+    source::{RepositoryMetadata, SourceRepositoryMapping, SourceRepositoryMappingProducer},
 };
 use anyhow::Result;
 use serde::Deserialize;
 use std::path::PathBuf;
-use url::Url;
 
 #[derive(Debug, Deserialize)]
 pub(crate) struct StaticList {
@@ -20,10 +21,12 @@ impl SourceRepositoryMappingProducer for StaticList {
             .map(|r| SourceRepositoryMapping {
                 path: match &r.path {
                     Some(p) => p.clone(),
-                    None => r.git_url.path().into(),
+                    None => r.git_url.url().path().into(),
                 },
                 ref_match: r.ref_match.clone(),
+                ref_rewrites: r.ref_rewrites.clone(),
                 git_url: r.git_url.clone(),
+                metadata: RepositoryMetadata::default(),
             })
             .collect())
     }
@@ -31,7 +34,8 @@ impl SourceRepositoryMappingProducer for StaticList {
 
 #[derive(Debug, Deserialize)]
 struct Repository {
-    pub git_url: Url,
+    pub git_url: RemoteAddress,
     pub path: Option<PathBuf>,
     pub ref_match: Option<Ruleset>,
+    pub ref_rewrites: Option<RefRewrites>,
 }
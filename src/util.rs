@@ -9,12 +9,104 @@ pub(crate) fn safe_display_url(mut url: Url) -> String {
     url.to_string()
 }
 
+pub(crate) fn remove_url_credentials(mut u: Url) -> Url {
+    // URLs that cannot have credentials, e.g. `file://`, have none to remove
+    let _ = u.set_username("");
+    let _ = u.set_password(None);
+    u
+}
+
+/// Removes the credentials that must not be stored in a repository's config from `url`: any
+/// password, and the username over HTTP(S), where tokens may be given as the username alone.
+/// Other usernames, e.g. for SSH, are kept. Returns `None` if there are no such credentials.
+pub(crate) fn remove_stored_credentials(url: &Url) -> Option<Url> {
+    let http = url.scheme().starts_with("http");
+    let has_credentials = url.password().is_some() || (http && !url.username().is_empty());
+    has_credentials.then(|| {
+        let mut u = url.clone();
+        let _ = u.set_password(None);
+        if http {
+            let _ = u.set_username("");
+        }
+        u
+    })
+}
+
 pub(crate) fn git_timestamp(t: Time) -> DateTime<FixedOffset> {
     let tz = FixedOffset::east_opt(t.offset_minutes() * 60).unwrap();
     let t = DateTime::from_timestamp(t.seconds(), 0).unwrap();
     t.with_timezone(&tz)
 }
 
+#[cfg(test)]
+pub(crate) mod testing {
+    use crate::{
+        config::RepositoryMapping,
+        source::{RepositoryMetadata, SourceRepositoryMapping},
+    };
+    use git2::{Oid, Repository, Signature, Time};
+    use std::path::{Path, PathBuf};
+
+    /// A repository at `url`, discovered by a provider that knows the given metadata about it.
+    pub(crate) fn source_mapping(
+        url: &str,
+        metadata: RepositoryMetadata,
+    ) -> SourceRepositoryMapping {
+        SourceRepositoryMapping {
+            path: PathBuf::new(),
+            ref_match: None,
+            ref_rewrites: None,
+            git_url: url.parse().unwrap(),
+            metadata,
+        }
+    }
+
+    /// A mapping that mirrors all branches and tags of the repository at `source` into `path`.
+    pub(crate) fn mapping(source: &Path, path: &Path) -> RepositoryMapping {
+        RepositoryMapping {
+            path: path.to_path_buf(),
+            ref_match: toml::from_str(
+                "rules = [{ type = 'glob', expr = 'refs/heads/**' }, { type = 'glob', expr = 'refs/tags/**' }]",
+            )
+            .unwrap(),
+            ref_rewrites: Default::default(),
+            follow_tags: false,
+            deleted_refs: Default::default(),
+            snapshots: Default::default(),
+            refuse_non_fast_forward: false,
+            retry: Default::default(),
+            timeout: None,
+            ssh: Default::default(),
+            credentials: None,
+            git_url: source.to_str().unwrap().parse().unwrap(),
+        }
+    }
+
+    /// Creates a non-bare repository containing a single commit (with the given commit time)
+    /// on `refs/heads/main`.
+    pub(crate) fn repo_with_commit(path: &Path, time: i64) -> (Repository, Oid) {
+        let repo = Repository::init(path).unwrap();
+        let oid = commit(&repo, "refs/heads/main", time, &[]);
+        repo.set_head("refs/heads/main").unwrap();
+        (repo, oid)
+    }
+
+    /// Creates a commit with an empty tree on the given ref.
+    pub(crate) fn commit(repo: &Repository, refname: &str, time: i64, parents: &[Oid]) -> Oid {
+        let sig = Signature::new("Test", "test@example.com", &Time::new(time, 0)).unwrap();
+        let tree = repo
+            .find_tree(repo.treebuilder(None).unwrap().write().unwrap())
+            .unwrap();
+        let parents: Vec<_> = parents
+            .iter()
+            .map(|p| repo.find_commit(*p).unwrap())
+            .collect();
+        let parents: Vec<_> = parents.iter().collect();
+        repo.commit(Some(refname), &sig, &sig, "test", &tree, &parents)
+            .unwrap()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;